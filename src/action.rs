@@ -0,0 +1,203 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+use crate::cli::{DisposeArgs, RemoveAction, SymlinkTarget};
+use crate::config::Config;
+use crate::db::{DeletionRow, Db, FileRow};
+use crate::link;
+use crate::protect::Protected;
+use crate::quarantine::Quarantine;
+use crate::scan;
+use crate::trash;
+use crate::verify::{self, KeptFileChanged};
+
+/// What to do with each file marked for deletion.
+#[derive(Clone)]
+pub struct ActionOptions {
+    /// Only verify and record what would be done.
+    pub dry_run: bool,
+    /// Session the audit log entries belong to.
+    pub session_id: i64,
+    pub action: RemoveAction,
+    pub symlink_target: SymlinkTarget,
+    /// Destination of `RemoveAction::Quarantine`.
+    pub quarantine: Option<Quarantine>,
+    /// Compare `keep` and each target byte by byte before acting.
+    pub paranoid: bool,
+    /// Paths no target may match.
+    pub protected: Protected,
+}
+
+/// Record a new session for the running command; its id tags the audit
+/// log entries (and quarantined files) of this run.
+pub fn start_session(db: &Db) -> Result<i64> {
+    let command_line: Vec<String> = std::env::args().collect();
+    db.start_session(&command_line.join(" "), chrono::Utc::now().timestamp())
+}
+
+/// Options for the `--action` / `--quarantine` flags of `remove` and
+/// `apply`, in a new session. The action falls back to the config's
+/// `remove_action`.
+pub fn options(args: &DisposeArgs, config: &Config, db: &Db) -> Result<ActionOptions> {
+    let session_id = start_session(db)?;
+    let (action, quarantine) = match &args.quarantine {
+        Some(_) if args.dry_run => (RemoveAction::Quarantine, None),
+        Some(dir) => (
+            RemoveAction::Quarantine,
+            Some(Quarantine::open(dir, session_id)?),
+        ),
+        None => (
+            args.action.or(config.remove_action).unwrap_or_default(),
+            None,
+        ),
+    };
+    Ok(ActionOptions {
+        dry_run: args.dry_run,
+        session_id,
+        action,
+        symlink_target: args.symlink_target,
+        quarantine,
+        paranoid: args.paranoid,
+        protected: Protected::load(config, db)?,
+    })
+}
+
+/// Get rid of `target`, a duplicate of `keep`, update its DB row and record
+/// it in the `deletions` audit log. `rule` describes the rule that chose
+/// `keep`, None for a manual decision.
+///
+/// A protected target is refused. Both files are then checked against their
+/// rows (size and mtime, plus a byte-by-byte comparison when `paranoid`). A
+/// changed target is skipped with an error; a changed or missing `keep`
+/// fails with `KeptFileChanged`.
+///
+/// A symbolic link target (from `remove --trees`) is checked as a link and
+/// moved or unlinked without a content check. The link actions leave it
+/// alone: a link already stands at its path.
+pub fn apply(
+    opts: &ActionOptions,
+    keep: &FileRow,
+    target: &FileRow,
+    rule: Option<&str>,
+    db: &Db,
+) -> Result<()> {
+    if opts.action == RemoveAction::Hardlink && target.shares_inode_with(keep) {
+        return Ok(());
+    }
+    let link = target.link_target.is_some();
+    if link && matches!(opts.action, RemoveAction::Hardlink | RemoveAction::Symlink) {
+        return Ok(());
+    }
+    if opts.protected.contains(&target.canonical_path) {
+        bail!("{} is protected", target.canonical_path);
+    }
+    let target_path = Path::new(&target.canonical_path);
+    verify::unchanged(keep).map_err(|e| KeptFileChanged(format!("{:#}", e)))?;
+    verify::unchanged(target)?;
+    if opts.paranoid
+        && !link
+        && !target.shares_inode_with(keep)
+        && !verify::same_content(Path::new(&keep.canonical_path), target_path)?
+    {
+        bail!(
+            "{} differs from the kept copy {}",
+            target.canonical_path,
+            keep.canonical_path
+        );
+    }
+    if !opts.dry_run {
+        dispose(opts, keep, target, db)?;
+    }
+    record(opts, keep, target, rule, action_name(opts.action), db)
+}
+
+/// Append `target`, acted on with `action` in favour of `keep`, to the
+/// `deletions` audit log of the session in `opts`.
+pub fn record(
+    opts: &ActionOptions,
+    keep: &FileRow,
+    target: &FileRow,
+    rule: Option<&str>,
+    action: &str,
+    db: &Db,
+) -> Result<()> {
+    db.record_deletion(&DeletionRow {
+        id: 0,
+        session_id: opts.session_id,
+        path: target.canonical_path.clone(),
+        size: target.size,
+        dev: target.dev,
+        inode: target.inode,
+        full_hash: target.full_hash.clone(),
+        kept_path: keep.canonical_path.clone(),
+        rule: rule.map(str::to_owned),
+        action: action.to_owned(),
+        dry_run: opts.dry_run,
+        deleted_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+fn dispose(opts: &ActionOptions, keep: &FileRow, target: &FileRow, db: &Db) -> Result<()> {
+    let target_path = Path::new(&target.canonical_path);
+    match opts.action {
+        RemoveAction::Trash => {
+            trash::trash(target_path)?;
+            db.delete_file(target.id)?;
+        }
+        RemoveAction::Quarantine => {
+            let quarantine = opts
+                .quarantine
+                .as_ref()
+                .context("no quarantine directory given")?;
+            quarantine
+                .store(target, db)
+                .with_context(|| format!("quarantining {}", target.canonical_path))?;
+            db.delete_file(target.id)?;
+        }
+        RemoveAction::Delete => {
+            std::fs::remove_file(target_path)
+                .with_context(|| format!("deleting {}", target.canonical_path))?;
+            db.delete_file(target.id)?;
+        }
+        RemoveAction::Hardlink => {
+            link::hard_link_replace(Path::new(&keep.canonical_path), target_path)
+                .with_context(|| format!("linking {}", target.canonical_path))?;
+
+            // The path now points at keep's inode; hashes are unchanged.
+            let meta = scan::file_meta(&std::fs::metadata(target_path)?);
+            db.update_file_meta(target.id, &meta)?;
+            if let (Some(dev), Some(inode)) = (meta.dev, meta.inode) {
+                db.set_nlink(dev, inode, meta.nlink)?;
+            }
+        }
+        RemoveAction::Symlink => {
+            let link_target = link::symlink_replace(
+                Path::new(&keep.canonical_path),
+                target_path,
+                opts.symlink_target,
+            )
+            .with_context(|| format!("symlinking {}", target.canonical_path))?;
+
+            // The row now describes the link itself; scan keeps it as a link.
+            let meta = scan::file_meta(&std::fs::symlink_metadata(target_path)?);
+            db.mark_symlink(target.id, &meta, &link_target)?;
+        }
+    }
+    // Every action takes one link away from the target's old inode; the
+    // other paths to it keep their rows.
+    if let Some((dev, inode)) = target.inode_key() {
+        db.drop_link(dev, inode)?;
+    }
+    Ok(())
+}
+
+/// Name of an action as stored in the audit log.
+fn action_name(action: RemoveAction) -> &'static str {
+    match action {
+        RemoveAction::Trash => "trash",
+        RemoveAction::Delete => "delete",
+        RemoveAction::Hardlink => "hardlink",
+        RemoveAction::Symlink => "symlink",
+        RemoveAction::Quarantine => "quarantine",
+    }
+}
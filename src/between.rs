@@ -0,0 +1,353 @@
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::action::{self, ActionOptions};
+use crate::db::{Db, DuplicateGroup, FileRow};
+use crate::tui::{self, fmt_size};
+use crate::verify::KeptFileChanged;
+
+/// Which directory's copies are kept.
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    A,
+    B,
+}
+
+/// What keeping one side's copies does to a group: the copy the others are
+/// checked against, and the copies under the other side to act on.
+struct Resolution<'g> {
+    group: &'g DuplicateGroup,
+    keep: usize,
+    targets: Vec<usize>,
+}
+
+/// Totals of keeping one side's copies.
+struct Outcome<'g> {
+    groups: Vec<Resolution<'g>>,
+    files: usize,
+    freed: i64,
+    /// Copies on the other side left alone because they are protected.
+    protected: usize,
+}
+
+/// `remove --between A B`: resolve every group with copies under both `a`
+/// and `b` with one decision taken in a small TUI, then act on it.
+pub fn run(
+    groups: &[DuplicateGroup],
+    a: &Path,
+    b: &Path,
+    opts: &ActionOptions,
+    db: &Db,
+) -> Result<()> {
+    let a = dir(a)?;
+    let b = dir(b)?;
+    if a.starts_with(&b) || b.starts_with(&a) {
+        bail!(
+            "{} and {} overlap; --between needs two separate trees",
+            a.display(),
+            b.display()
+        );
+    }
+    let under = |f: &FileRow, dir: &Path| Path::new(&f.canonical_path).starts_with(dir);
+    let pairs: Vec<&DuplicateGroup> = groups
+        .iter()
+        .filter(|g| g.files.iter().any(|f| under(f, &a)) && g.files.iter().any(|f| under(f, &b)))
+        .collect();
+    let only_in_b = only_in(&b, &a, db)?;
+
+    if pairs.is_empty() {
+        println!(
+            "No duplicate groups have copies under both {} and {}.",
+            a.display(),
+            b.display()
+        );
+        if !only_in_b.is_empty() {
+            println!(
+                "{} files under {} have no copy under {}.",
+                only_in_b.len(),
+                b.display(),
+                a.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let keep_a = outcome(&pairs, &a, &b, opts);
+    let keep_b = outcome(&pairs, &b, &a, opts);
+    let mut terminal = tui::enter()?;
+    let choice = choose(
+        &mut terminal,
+        [&a, &b],
+        pairs.len(),
+        [&keep_a, &keep_b],
+        &only_in_b,
+        opts.dry_run,
+    );
+    tui::leave(&mut terminal)?;
+
+    let (outcome, kept_dir) = match choice? {
+        Some(Side::A) => (keep_a, &a),
+        Some(Side::B) => (keep_b, &b),
+        None => {
+            println!("Nothing was removed.");
+            return Ok(());
+        }
+    };
+    apply(&outcome, kept_dir, opts, db);
+    Ok(())
+}
+
+fn dir(path: &Path) -> Result<PathBuf> {
+    let dir = path
+        .canonicalize()
+        .with_context(|| format!("--between {}", path.display()))?;
+    if !dir.is_dir() {
+        bail!("--between {} is not a directory", dir.display());
+    }
+    Ok(dir)
+}
+
+/// Files under `dir` with no copy under `other`, by content as of the last
+/// scan.
+fn only_in(dir: &Path, other: &Path, db: &Db) -> Result<Vec<FileRow>> {
+    let other_hashes: HashSet<String> = db
+        .files_under(&other.to_string_lossy())?
+        .into_iter()
+        .filter_map(|f| f.full_hash)
+        .collect();
+    Ok(db
+        .files_under(&dir.to_string_lossy())?
+        .into_iter()
+        .filter(|f| {
+            f.full_hash
+                .as_ref()
+                .is_none_or(|h| !other_hashes.contains(h))
+        })
+        .collect())
+}
+
+/// Keep the copies under `kept` and act on those under `other`; protected
+/// copies and copies outside both directories are left alone.
+fn outcome<'g>(
+    pairs: &[&'g DuplicateGroup],
+    kept: &Path,
+    other: &Path,
+    opts: &ActionOptions,
+) -> Outcome<'g> {
+    let mut out = Outcome {
+        groups: Vec::new(),
+        files: 0,
+        freed: 0,
+        protected: 0,
+    };
+    for &group in pairs {
+        let under =
+            |i: &usize, dir: &Path| Path::new(&group.files[*i].canonical_path).starts_with(dir);
+        let protected = |i: &usize| opts.protected.contains(&group.files[*i].canonical_path);
+        let kept_copies: Vec<usize> = (0..group.files.len()).filter(|i| under(i, kept)).collect();
+        let keep = kept_copies
+            .iter()
+            .copied()
+            .find(|i| !protected(i))
+            .unwrap_or(kept_copies[0]);
+        let (locked, targets): (Vec<usize>, Vec<usize>) = (0..group.files.len())
+            .filter(|i| under(i, other))
+            .partition(protected);
+        out.protected += locked.len();
+        out.files += targets.len();
+        out.freed += group.bytes_freed(&targets);
+        out.groups.push(Resolution {
+            group,
+            keep,
+            targets,
+        });
+    }
+    out
+}
+
+/// Show both outcomes and the files only under B; returns the side whose
+/// copies to keep, or None when the user quits.
+fn choose(
+    terminal: &mut tui::Term,
+    dirs: [&Path; 2],
+    pairs: usize,
+    outcomes: [&Outcome; 2],
+    only_in_b: &[FileRow],
+    dry_run: bool,
+) -> Result<Option<Side>> {
+    let mut pending: Option<Side> = None;
+    let mut list_state = ListState::default();
+    list_state.select(Some(0));
+    let only_bytes: i64 = only_in_b.iter().map(|f| f.size).sum();
+    let items: Vec<ListItem> = only_in_b
+        .iter()
+        .map(|f| ListItem::new(format!("{}  ({})", f.canonical_path, fmt_size(f.size))))
+        .collect();
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Length(4),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ])
+                .split(f.area());
+
+            let dry_tag = if dry_run { " [DRY RUN]" } else { "" };
+            let header = Paragraph::new(vec![
+                Line::from(format!("A  {}", dirs[0].display())),
+                Line::from(format!("B  {}", dirs[1].display())),
+                Line::from(format!(
+                    "{} duplicate groups have copies under both.",
+                    pairs
+                )),
+            ])
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" fdedupe — remove --between{} ", dry_tag)),
+            );
+            f.render_widget(header, chunks[0]);
+
+            let choice = |side: Side, key: &'static str, from: &str, to: &str| {
+                let o = outcomes[side as usize];
+                let style = if pending == Some(side) {
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let mut spans = vec![
+                    Span::styled(key, Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!(
+                            "  Keep {}'s copies: remove {} files under {}, freeing {}",
+                            from,
+                            o.files,
+                            to,
+                            fmt_size(o.freed)
+                        ),
+                        style,
+                    ),
+                ];
+                if o.protected > 0 {
+                    spans.push(Span::raw(format!(" ({} protected stay)", o.protected)));
+                }
+                Line::from(spans)
+            };
+            let choices = Paragraph::new(vec![
+                choice(Side::A, "a", "A", "B"),
+                choice(Side::B, "b", "B", "A"),
+            ])
+            .block(Block::default().borders(Borders::ALL).title(" Decision "));
+            f.render_widget(choices, chunks[1]);
+
+            let list = List::new(items.clone())
+                .block(Block::default().borders(Borders::ALL).title(format!(
+                    " Only in B: {} files, {} ",
+                    only_in_b.len(),
+                    fmt_size(only_bytes)
+                )))
+                .highlight_style(Style::default().bg(Color::DarkGray));
+            f.render_stateful_widget(list, chunks[2], &mut list_state);
+
+            let footer = match pending {
+                Some(side) => {
+                    let o = outcomes[side as usize];
+                    format!(
+                        "  Remove {} files, freeing {}?   y confirm   n cancel",
+                        o.files,
+                        fmt_size(o.freed)
+                    )
+                }
+                None => "  a keep A's copies   b keep B's copies   ↑↓ scroll   q quit".into(),
+            };
+            f.render_widget(
+                Paragraph::new(Line::from(footer)).style(Style::default().fg(Color::DarkGray)),
+                chunks[3],
+            );
+        })?;
+
+        if let Some(key) = tui::next_key(Duration::from_millis(100))? {
+            let cur = list_state.selected().unwrap_or(0);
+            match (pending, key.code) {
+                (Some(side), KeyCode::Char('y')) => return Ok(Some(side)),
+                (Some(_), KeyCode::Char('n') | KeyCode::Esc) => pending = None,
+                (None, KeyCode::Char('a')) => pending = Some(Side::A),
+                (None, KeyCode::Char('b')) => pending = Some(Side::B),
+                (None, KeyCode::Char('q') | KeyCode::Esc) => return Ok(None),
+                (_, KeyCode::Up) => list_state.select(Some(cur.saturating_sub(1))),
+                (_, KeyCode::Down) => {
+                    list_state.select(Some((cur + 1).min(items.len().saturating_sub(1))))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Hand every target of `outcome` to `action::apply` and print what was
+/// done.
+fn apply(outcome: &Outcome, kept_dir: &Path, opts: &ActionOptions, db: &Db) {
+    let dry = opts.dry_run;
+    let rule = format!("keep copies under {}", kept_dir.display());
+    let mut files = 0;
+    let mut freed = 0;
+    let mut failures = Vec::new();
+
+    for res in &outcome.groups {
+        let keep = &res.group.files[res.keep];
+        println!("Keep: {}", keep.canonical_path);
+        let mut done = Vec::new();
+        for &i in &res.targets {
+            let target = &res.group.files[i];
+            match action::apply(opts, keep, target, Some(&rule), db) {
+                Ok(()) => {
+                    println!(
+                        "  {} {}",
+                        if dry { "would remove" } else { "removed" },
+                        target.canonical_path
+                    );
+                    done.push(i);
+                }
+                Err(e) => {
+                    failures.push(format!("Failed: {:#}", e));
+                    if e.is::<KeptFileChanged>() {
+                        break;
+                    }
+                }
+            }
+        }
+        files += done.len();
+        freed += res.group.bytes_freed(&done);
+    }
+
+    for msg in &failures {
+        eprintln!("{}", msg);
+    }
+    println!();
+    println!(
+        "{}{} {} files in {} groups, freeing {}",
+        if dry { "[DRY RUN] " } else { "" },
+        if dry { "Would remove" } else { "Removed" },
+        files,
+        outcome.groups.len(),
+        fmt_size(freed)
+    );
+    if !failures.is_empty() {
+        println!("{} refused or failed (see above)", failures.len());
+    }
+}
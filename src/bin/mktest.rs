@@ -1,15 +1,15 @@
-/// mktest — generate deterministic test data under testdata/
-///
-/// Run with: cargo run --bin mktest
-///
-/// Wipes and recreates testdata/ from scratch. Expected duplicate groups:
-///   "hello world\n"   — 3 files (4 with --hidden)  — 12 bytes each
-///   "roses are red\n" — 2 files                    — 15 bytes each
-///   128 KB 0xAB block — 2 files                    — 131072 bytes each
-///
-/// Symlink layout (Unix only):
-///   testdata/gamma/alpha_link -> ../alpha
-///   Used to test --follow-symlinks behaviour.
+//! mktest — generate deterministic test data under testdata/
+//!
+//! Run with: cargo run --bin mktest
+//!
+//! Wipes and recreates testdata/ from scratch. Expected duplicate groups:
+//!   "hello world\n"   — 3 files (4 with --hidden)  — 12 bytes each
+//!   "roses are red\n" — 2 files                    — 15 bytes each
+//!   128 KB 0xAB block — 2 files                    — 131072 bytes each
+//!
+//! Symlink layout (Unix only):
+//!   testdata/gamma/alpha_link -> ../alpha
+//!   Used to test --follow-symlinks behaviour.
 
 use std::fs;
 use std::io::Write;
@@ -56,7 +56,7 @@ fn main() {
     // Used to exercise --follow-symlinks (with canonical-path dedup) and
     // the default behaviour of not following symlinks.
     #[cfg(unix)]
-    std::os::unix::fs::symlink("../alpha", &gamma.join("alpha_link"))
+    std::os::unix::fs::symlink("../alpha", gamma.join("alpha_link"))
         .unwrap_or_else(|e| eprintln!("warning: could not create symlink: {e}"));
 
     // ── large/ ────────────────────────────────────────────────────────────────
@@ -1,4 +1,5 @@
-use clap::{Args, Parser, Subcommand};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -20,6 +21,26 @@ pub enum Command {
     List(ListArgs),
     /// Remove duplicate files interactively
     Remove(RemoveArgs),
+    /// Replace every duplicate with a hard link to one kept copy
+    Link(LinkArgs),
+    /// Share the data of duplicates on disk without changing any path
+    Dedupe(DedupeArgs),
+    /// Move quarantined files back to their original paths
+    Restore(RestoreArgs),
+    /// Permanently delete old files from quarantine
+    Purge(PurgeArgs),
+    /// Show what remove, link and dedupe did, per session
+    History(HistoryArgs),
+    /// Write keep/delete decisions for every duplicate group to a plan file
+    Plan(PlanArgs),
+    /// Carry out the decisions of a plan file written by `plan`
+    Apply(ApplyArgs),
+    /// List, add, edit, remove and test the priority rules
+    Rules(RulesArgs),
+    /// Manage paths that are never removed, whatever the rules decide
+    Protect(ProtectArgs),
+    /// Report directories that are largely duplicates of each other
+    Overlap(OverlapArgs),
 }
 
 #[derive(Args)]
@@ -50,6 +71,28 @@ pub struct ScanArgs {
     /// Exclude files matching these glob patterns
     #[arg(long, value_name = "GLOB")]
     pub exclude: Vec<String>,
+
+    /// Worker threads per pipeline stage (default: number of CPUs)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+
+    /// Database rows written per transaction (default: 1000)
+    #[arg(long, value_name = "N")]
+    pub batch_size: Option<usize>,
+
+    /// Which collision candidates get a full hash (default: all)
+    #[arg(long, value_enum)]
+    pub full_hash_scope: Option<FullHashScope>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FullHashScope {
+    /// Every candidate in the database
+    #[default]
+    All,
+    /// Only candidates with at least one copy in a directory visited by this scan
+    Scanned,
 }
 
 #[derive(Args)]
@@ -72,7 +115,470 @@ pub struct ListArgs {
 
 #[derive(Args)]
 pub struct RemoveArgs {
+    #[command(flatten)]
+    pub dispose: DisposeArgs,
+
+    /// Act only on groups the priority rules decide, without a terminal, and
+    /// print a summary. Exit status: 0 all groups resolved, 3 nothing to do,
+    /// 4 some groups left unresolved, 5 some files failed
+    #[arg(long)]
+    pub batch: bool,
+
+    /// Order in which the groups are shown
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: GroupSort,
+
+    /// Resolve every group with copies under both directories at once, by
+    /// keeping the copies under one of them
+    #[arg(long, num_args = 2, value_names = ["A", "B"], conflicts_with = "batch")]
+    pub between: Option<Vec<PathBuf>>,
+
+    /// Handle directory trees with identical content as units: keep one
+    /// copy of each and remove the others. Trees are always shown largest
+    /// first, and the group filters do not apply to them
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "batch", "between", "sort", "min_size", "prefix", "extensions", "under"
+        ]
+    )]
+    pub trees: bool,
+
+    #[command(flatten)]
+    pub filter: GroupFilter,
+}
+
+/// Which duplicate groups `remove` goes through.
+#[derive(Args, Default)]
+pub struct GroupFilter {
+    /// Only groups of files at least this large, e.g. 10M
+    #[arg(long, value_name = "SIZE", value_parser = crate::tui::parse_size)]
+    pub min_size: Option<i64>,
+
+    /// Only groups with every copy under this directory
+    #[arg(long, value_name = "DIR")]
+    pub prefix: Option<PathBuf>,
+
+    /// Only groups with a copy with this extension (repeatable)
+    #[arg(long = "ext", value_name = "EXT")]
+    pub extensions: Vec<String>,
+
+    /// Only groups with at least one copy under this directory
+    #[arg(long, value_name = "DIR")]
+    pub under: Option<PathBuf>,
+}
+
+/// Order of the duplicate groups in `remove`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum GroupSort {
+    /// Most bytes freed by keeping one copy first
+    #[default]
+    Wasted,
+    /// Largest files first
+    Size,
+    /// Most copies first
+    Copies,
+    /// By path, alphabetically
+    Path,
+}
+
+impl GroupSort {
+    pub fn name(self) -> &'static str {
+        match self {
+            GroupSort::Wasted => "wasted",
+            GroupSort::Size => "size",
+            GroupSort::Copies => "copies",
+            GroupSort::Path => "path",
+        }
+    }
+
+    /// The order after this one, for cycling through them in the TUI.
+    pub fn next(self) -> Self {
+        match self {
+            GroupSort::Wasted => GroupSort::Size,
+            GroupSort::Size => GroupSort::Copies,
+            GroupSort::Copies => GroupSort::Path,
+            GroupSort::Path => GroupSort::Wasted,
+        }
+    }
+}
+
+/// How files marked for deletion are disposed of; shared by `remove` and
+/// `apply`.
+#[derive(Args)]
+pub struct DisposeArgs {
     /// Show what would be deleted without actually deleting
     #[arg(long)]
     pub dry_run: bool,
+
+    /// What to do with each file marked for deletion (default: trash)
+    #[arg(long, value_enum)]
+    pub action: Option<RemoveAction>,
+
+    /// Move files marked for deletion under this directory, mirroring their
+    /// original paths, so `restore` can put them back
+    #[arg(long, value_name = "DIR", conflicts_with = "action")]
+    pub quarantine: Option<PathBuf>,
+
+    /// Compare each file byte by byte with the kept copy before acting on it
+    #[arg(long)]
+    pub paranoid: bool,
+
+    /// How `--action symlink` points links at the kept copy
+    #[arg(long, value_enum, default_value_t)]
+    pub symlink_target: SymlinkTarget,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RemoveAction {
+    /// Move the file to the freedesktop.org trash
+    #[default]
+    Trash,
+    /// Delete the file permanently
+    Delete,
+    /// Replace the file with a hard link to the kept copy
+    Hardlink,
+    /// Replace the file with a symbolic link to the kept copy
+    Symlink,
+    /// Move the file into a quarantine directory (set by --quarantine)
+    #[value(skip)]
+    #[serde(skip)]
+    Quarantine,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum SymlinkTarget {
+    /// Path relative to the link's directory
+    #[default]
+    Relative,
+    /// Canonical path of the kept copy
+    Absolute,
+}
+
+#[derive(Args)]
+pub struct LinkArgs {
+    /// Show what would be linked without changing any file
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Compare each file byte by byte with the kept copy before linking it
+    #[arg(long)]
+    pub paranoid: bool,
+}
+
+#[derive(Args)]
+pub struct DedupeArgs {
+    /// Share extents through the kernel's FIDEDUPERANGE ioctl (btrfs, XFS)
+    #[arg(long)]
+    pub reflink: bool,
+
+    /// What to do with duplicates on filesystems without extent sharing
+    #[arg(long, value_enum, default_value_t)]
+    pub fallback: DedupeFallback,
+
+    /// Show what would be shared without calling the kernel
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum DedupeFallback {
+    /// Report the files and leave them alone
+    #[default]
+    None,
+    /// Replace them with hard links to the kept copy
+    Hardlink,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("select").required(true).multiple(true)))]
+pub struct RestoreArgs {
+    /// Original paths to restore; a directory restores everything under it
+    #[arg(group = "select")]
+    pub paths: Vec<PathBuf>,
+
+    /// Restore every quarantined copy of the duplicate group with this full hash
+    #[arg(long, value_name = "FULL_HASH", group = "select")]
+    pub group: Option<String>,
+
+    /// Restore everything quarantined by one remove session
+    #[arg(long, value_name = "ID", group = "select")]
+    pub session: Option<i64>,
+
+    /// Show what would be restored without moving any file
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct PurgeArgs {
+    /// Delete files quarantined longer ago than this (e.g. 30d, 12h, 2w)
+    #[arg(long, value_name = "AGE", value_parser = crate::quarantine::parse_age)]
+    pub older_than: i64,
+
+    /// Show what would be deleted without deleting anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// Session to show the files of; omit to list sessions
+    pub session: Option<i64>,
+
+    /// Output format; csv and json export every file (of the session, if given)
+    #[arg(long, value_enum, default_value_t)]
+    pub format: HistoryFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum HistoryFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Args)]
+pub struct PlanArgs {
+    /// File to write the plan to (default: standard output)
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Plan format (default: from the output file's extension, else json)
+    #[arg(long, value_enum)]
+    pub format: Option<PlanFormat>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum PlanFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(Args)]
+pub struct ApplyArgs {
+    /// Plan file written by `plan` (JSON, or YAML when named *.yaml / *.yml)
+    pub plan: PathBuf,
+
+    #[command(flatten)]
+    pub dispose: DisposeArgs,
+}
+
+#[derive(Args)]
+pub struct RulesArgs {
+    #[command(subcommand)]
+    pub command: RulesCommand,
+}
+
+#[derive(Subcommand)]
+pub enum RulesCommand {
+    /// List all rules in the order they are applied
+    List,
+    /// Add a rule deciding which copy of a duplicate group to keep
+    Add {
+        /// Glob, regex or file-name glob, for the kinds that take one
+        pattern: Option<String>,
+        #[arg(short, long, value_enum, default_value_t)]
+        kind: RuleKind,
+        #[arg(short, long, default_value_t = 0, allow_negative_numbers = true)]
+        priority: i64,
+        /// Only apply the rule to groups under this directory
+        #[arg(long, value_name = "DIR")]
+        scope: Option<PathBuf>,
+        /// Which copies of a group must be under --scope
+        #[arg(long, value_enum, default_value_t)]
+        scope_match: ScopeMatch,
+        /// Keep this many copies instead of one
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        keep: u32,
+    },
+    /// Remove rules by id
+    Rm {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+    /// Change the kind, pattern, priority, scope or keep count of a rule
+    Edit {
+        id: i64,
+        #[arg(short, long, value_enum)]
+        kind: Option<RuleKind>,
+        #[arg(long)]
+        pattern: Option<String>,
+        #[arg(short, long, allow_negative_numbers = true)]
+        priority: Option<i64>,
+        #[arg(long, value_name = "DIR", conflicts_with = "no_scope")]
+        scope: Option<PathBuf>,
+        /// Make the rule apply everywhere again
+        #[arg(long)]
+        no_scope: bool,
+        #[arg(long, value_enum)]
+        scope_match: Option<ScopeMatch>,
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        keep: Option<u32>,
+    },
+    /// Show which rules match a path, and with what priority
+    Test {
+        path: PathBuf,
+    },
+    /// Write the rules in the database to a YAML file
+    Export {
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Load rules from a YAML file written by `rules export`
+    Import {
+        file: PathBuf,
+        /// Delete the existing rules first instead of adding to them
+        #[arg(long)]
+        replace: bool,
+    },
+}
+
+/// How many copies of a group must be under a rule's scope for it to apply.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScopeMatch {
+    /// Every copy
+    #[default]
+    All,
+    /// At least one copy
+    Any,
+}
+
+impl ScopeMatch {
+    pub fn name(self) -> &'static str {
+        match self {
+            ScopeMatch::All => "all",
+            ScopeMatch::Any => "any",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as ValueEnum>::value_variants()
+            .iter()
+            .copied()
+            .find(|m| m.name() == name)
+    }
+}
+
+/// What a rule prefers among the copies of a group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleKind {
+    /// Copies whose canonical path matches the glob
+    #[default]
+    Glob,
+    /// Copies whose canonical path matches the regular expression
+    Regex,
+    /// Copies whose file name matches the glob
+    Name,
+    /// Copies whose file name does not match the glob, e.g. '*copy*'
+    AvoidName,
+    /// The most recently modified copies
+    Newest,
+    /// The least recently modified copies
+    Oldest,
+    /// The copies with the shortest path
+    ShortestPath,
+    /// The copies with the fewest directories above them
+    Shallowest,
+    /// The copies taking the least space on disk (sparse or compressed)
+    SmallestOnDisk,
+}
+
+impl RuleKind {
+    /// Name as stored in the `rules` table and shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            RuleKind::Glob => "glob",
+            RuleKind::Regex => "regex",
+            RuleKind::Name => "name",
+            RuleKind::AvoidName => "avoid-name",
+            RuleKind::Newest => "newest",
+            RuleKind::Oldest => "oldest",
+            RuleKind::ShortestPath => "shortest-path",
+            RuleKind::Shallowest => "shallowest",
+            RuleKind::SmallestOnDisk => "smallest-on-disk",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as ValueEnum>::value_variants()
+            .iter()
+            .copied()
+            .find(|k| k.name() == name)
+    }
+
+    /// Whether the kind matches a pattern, rather than ordering the copies.
+    pub fn takes_pattern(self) -> bool {
+        matches!(
+            self,
+            RuleKind::Glob | RuleKind::Regex | RuleKind::Name | RuleKind::AvoidName
+        )
+    }
+}
+
+#[derive(Args)]
+pub struct ProtectArgs {
+    #[command(subcommand)]
+    pub command: ProtectCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ProtectCommand {
+    /// List protected globs, from the config file and the database
+    List,
+    /// Protect paths matching these globs, e.g. '/srv/archive/**'
+    Add {
+        #[arg(required = true)]
+        patterns: Vec<String>,
+    },
+    /// Stop protecting these globs
+    Rm {
+        #[arg(required = true)]
+        patterns: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct OverlapArgs {
+    /// Only pairs with a directory under one of these (default: every
+    /// scanned directory)
+    pub dirs: Vec<PathBuf>,
+
+    /// Only pairs where one directory is at least this many percent
+    /// contained in the other, by bytes
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    pub min_ratio: u8,
+
+    /// Only pairs sharing at least this many bytes, e.g. 100M
+    #[arg(long, value_name = "SIZE", value_parser = crate::tui::parse_size)]
+    pub min_shared: Option<i64>,
+
+    /// Show at most this many pairs
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Leave content found in more than this many directories out of the
+    /// shared counts; 0 for no limit (slow with files copied everywhere)
+    #[arg(long, value_name = "N", default_value_t = 32)]
+    pub max_holders: usize,
+
+    #[arg(long, value_enum, default_value_t)]
+    pub format: OverlapFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum OverlapFormat {
+    #[default]
+    Table,
+    Json,
 }
@@ -2,6 +2,8 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
+use crate::cli::{FullHashScope, RemoveAction, RuleKind, ScopeMatch};
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Config {
@@ -12,6 +14,36 @@ pub struct Config {
     pub hidden: bool,
     pub include: Vec<String>,
     pub exclude: Vec<String>,
+    pub jobs: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub full_hash_scope: Option<FullHashScope>,
+    pub remove_action: Option<RemoveAction>,
+    /// Globs of paths that are never removed or replaced.
+    pub protected: Vec<String>,
+    /// Priority rules applied alongside the ones in the database.
+    pub rules: Vec<RuleSpec>,
+}
+
+/// A priority rule as written in YAML: in the config file and in the files
+/// of `rules export` / `rules import`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSpec {
+    #[serde(default)]
+    pub kind: RuleKind,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pattern: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default)]
+    pub scope_match: ScopeMatch,
+    #[serde(default = "keep_one")]
+    pub keep: u32,
+}
+
+fn keep_one() -> u32 {
+    1
 }
 
 impl Config {
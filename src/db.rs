@@ -1,11 +1,68 @@
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection};
+use serde::Serialize;
 use std::path::Path;
 
+use crate::cli::{RuleKind, ScopeMatch};
+
 pub struct Db {
     conn: Connection,
 }
 
+/// Open transaction on a `Db`. Every `Db` call made while it is alive is part
+/// of it; it is rolled back on drop unless `commit` is called.
+pub struct Transaction<'a> {
+    tx: rusqlite::Transaction<'a>,
+}
+
+impl Transaction<'_> {
+    pub fn commit(self) -> Result<()> {
+        self.tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Groups many small writes into transactions of at most `limit` rows.
+/// Call `wrote` after each write and `commit` once at the end.
+pub struct WriteBatch<'a> {
+    db: &'a Db,
+    tx: Option<Transaction<'a>>,
+    rows: usize,
+    limit: usize,
+}
+
+impl<'a> WriteBatch<'a> {
+    pub fn new(db: &'a Db, limit: usize) -> Result<Self> {
+        Ok(Self {
+            db,
+            tx: Some(db.transaction()?),
+            rows: 0,
+            limit: limit.max(1),
+        })
+    }
+
+    /// Record `rows` written rows; commits and starts a new transaction once
+    /// the limit is reached.
+    pub fn wrote(&mut self, rows: usize) -> Result<()> {
+        self.rows += rows;
+        if self.rows >= self.limit {
+            if let Some(tx) = self.tx.take() {
+                tx.commit()?;
+            }
+            self.tx = Some(self.db.transaction()?);
+            self.rows = 0;
+        }
+        Ok(())
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        if let Some(tx) = self.tx.take() {
+            tx.commit()?;
+        }
+        Ok(())
+    }
+}
+
 // ── Row types ───────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone)]
@@ -13,6 +70,12 @@ pub struct DirectoryRow {
     pub id: i64,
     pub canonical_path: String,
     pub last_scanned: Option<i64>,
+    /// None for the directories scans started from.
+    pub parent_id: Option<i64>,
+    /// The last scan of this directory recorded every entry in it and
+    /// visited every subdirectory: nothing was skipped by the hidden,
+    /// include or exclude filters or by errors, and the scan was recursive.
+    pub complete: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -23,15 +86,121 @@ pub struct FileRow {
     pub canonical_path: String,
     pub size: i64,
     pub modified_at: i64,
+    /// `st_dev` / `st_ino`; NULL on platforms without inode numbers.
+    pub dev: Option<i64>,
+    pub inode: Option<i64>,
+    pub nlink: i64,
     pub fast_hash: Option<String>,
     pub full_hash: Option<String>,
+    /// Target of a symbolic link, as stored in the link; None for regular
+    /// files. Links are never hashed.
+    pub link_target: Option<String>,
+}
+
+impl FileRow {
+    /// Identity of the data on disk: paths with the same key are hard links.
+    pub fn inode_key(&self) -> Option<(i64, i64)> {
+        Some((self.dev?, self.inode?))
+    }
+
+    pub fn shares_inode_with(&self, other: &FileRow) -> bool {
+        self.inode_key().is_some() && self.inode_key() == other.inode_key()
+    }
+}
+
+/// Stat data recorded for a file.
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeta {
+    pub size: i64,
+    pub modified_at: i64,
+    pub dev: Option<i64>,
+    pub inode: Option<i64>,
+    pub nlink: i64,
+}
+
+/// A file moved into a quarantine directory by `remove --quarantine`.
+#[derive(Debug, Clone)]
+pub struct QuarantineRow {
+    pub id: i64,
+    pub session_id: i64,
+    pub original_path: String,
+    /// Quarantine directory the file was moved under.
+    pub quarantine_root: String,
+    pub quarantine_path: String,
+    pub size: i64,
+    pub modified_at: i64,
+    pub fast_hash: Option<String>,
+    pub full_hash: Option<String>,
+    pub quarantined_at: i64,
+}
+
+/// One entry of the `deletions` audit log.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletionRow {
+    pub id: i64,
+    pub session_id: i64,
+    pub path: String,
+    pub size: i64,
+    /// Inode of the file acted on, so hard links to it count once in totals.
+    pub dev: Option<i64>,
+    pub inode: Option<i64>,
+    pub full_hash: Option<String>,
+    pub kept_path: String,
+    /// Pattern of the rule that chose the kept copy; None if decided by hand.
+    pub rule: Option<String>,
+    /// `trash`, `delete`, `hardlink`, `symlink`, `quarantine` or `reflink`.
+    pub action: String,
+    pub dry_run: bool,
+    pub deleted_at: i64,
+}
+
+/// A session with totals over its audit log entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub id: i64,
+    pub command: String,
+    pub started_at: i64,
+    pub files: i64,
+    /// Size of the files acted on; paths to one inode count once.
+    pub bytes: i64,
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct RuleRow {
+    /// 0 for rules declared in the config file.
     pub id: i64,
+    pub kind: RuleKind,
+    /// Glob or regex for the kinds that take one, empty otherwise.
     pub pattern: String,
     pub priority: i64,
+    /// Canonical directory the rule is limited to; None = everywhere.
+    pub scope: Option<String>,
+    pub scope_match: ScopeMatch,
+    /// How many copies the rule keeps; it decides a group once at most
+    /// this many candidates are left.
+    pub keep: u32,
+}
+
+impl RuleRow {
+    /// How the rule is shown and recorded in the audit log: the bare
+    /// pattern for globs, otherwise the kind and its pattern, if any; then
+    /// the number of copies kept, if more than one, and the scope.
+    pub fn describe(&self) -> String {
+        let mut rule = match self.kind {
+            RuleKind::Glob => self.pattern.clone(),
+            kind if kind.takes_pattern() => format!("{} {}", kind.name(), self.pattern),
+            kind => kind.name().to_owned(),
+        };
+        if self.keep > 1 {
+            rule += &format!(" keep {}", self.keep);
+        }
+        match (&self.scope, self.scope_match) {
+            (None, _) => rule,
+            (Some(scope), ScopeMatch::All) => format!("{} (in {})", rule, scope),
+            (Some(scope), ScopeMatch::Any) => format!("{} (any in {})", rule, scope),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +209,77 @@ pub struct DuplicateGroup {
     pub files: Vec<FileRow>,
 }
 
+impl DuplicateGroup {
+    /// Number of separate copies on disk; hard links to one inode count once.
+    pub fn distinct_copies(&self) -> usize {
+        let mut seen = std::collections::HashSet::new();
+        self.files
+            .iter()
+            .filter(|f| f.inode_key().is_none_or(|k| seen.insert(k)))
+            .count()
+    }
+
+    /// Every path is a hard link to the same inode: nothing to reclaim.
+    pub fn is_hard_linked(&self) -> bool {
+        self.distinct_copies() <= 1
+    }
+
+    /// Bytes freed by keeping a single copy.
+    pub fn reclaimable_bytes(&self) -> i64 {
+        let size = self.files.first().map_or(0, |f| f.size);
+        size * (self.distinct_copies() as i64 - 1).max(0)
+    }
+
+    /// Bytes actually freed by removing the files at `delete` (indexes into
+    /// `files`).
+    pub fn bytes_freed(&self, delete: &[usize]) -> i64 {
+        bytes_freed(&self.files, delete)
+    }
+}
+
+/// Bytes actually freed by removing the files at `delete` (indexes into
+/// `files`). An inode is only freed when none of its paths survive.
+pub fn bytes_freed(files: &[FileRow], delete: &[usize]) -> i64 {
+    let delete: std::collections::HashSet<usize> = delete.iter().copied().collect();
+    // Paths to each inode in `files`, and how many of them are deleted.
+    let mut links: std::collections::HashMap<(i64, i64), (i64, i64)> =
+        std::collections::HashMap::new();
+    for (i, file) in files.iter().enumerate() {
+        if let Some(key) = file.inode_key() {
+            let entry = links.entry(key).or_default();
+            entry.0 += 1;
+            entry.1 += i64::from(delete.contains(&i));
+        }
+    }
+    let mut freed = 0;
+    let mut counted = std::collections::HashSet::new();
+    for &i in &delete {
+        let file = &files[i];
+        let Some(key) = file.inode_key() else {
+            freed += file.size;
+            continue;
+        };
+        if !counted.insert(key) {
+            continue;
+        }
+        let (paths, deleted_links) = links[&key];
+        // Links outside the database keep the data alive too.
+        if deleted_links == paths && deleted_links >= file.nlink {
+            freed += file.size;
+        }
+    }
+    freed
+}
+
+/// Columns read by `file_from_row`, in order.
+const FILE_COLUMNS: &str =
+    "id, directory_id, name, canonical_path, size, modified_at, dev, inode, nlink, \
+     fast_hash, full_hash, link_target";
+
+/// SQL expression identifying the data behind a `files` row: hard links to
+/// one inode share it, rows without inode numbers are always distinct.
+const INODE_KEY: &str = "COALESCE(dev || ':' || inode, 'id:' || id)";
+
 // ── Open / schema ────────────────────────────────────────────────────────────
 
 impl Db {
@@ -49,9 +289,18 @@ impl Db {
         conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA foreign_keys=ON;")?;
         let db = Self { conn };
         db.create_schema()?;
+        db.migrate()?;
         Ok(db)
     }
 
+    /// Begin a transaction. `Db` methods take `&self`, so the guard does not
+    /// block other calls; they simply run inside it.
+    pub fn transaction(&self) -> Result<Transaction<'_>> {
+        Ok(Transaction {
+            tx: self.conn.unchecked_transaction()?,
+        })
+    }
+
     fn create_schema(&self) -> Result<()> {
         self.conn.execute_batch(
             "
@@ -59,7 +308,8 @@ impl Db {
                 id             INTEGER PRIMARY KEY,
                 canonical_path TEXT NOT NULL UNIQUE,
                 last_scanned   INTEGER,
-                parent_id      INTEGER REFERENCES directories(id)
+                parent_id      INTEGER REFERENCES directories(id),
+                complete       INTEGER NOT NULL DEFAULT 0
             );
 
             CREATE INDEX IF NOT EXISTS idx_directories_parent ON directories(parent_id);
@@ -71,8 +321,12 @@ impl Db {
                 canonical_path TEXT NOT NULL UNIQUE,
                 size           INTEGER NOT NULL,
                 modified_at    INTEGER NOT NULL,
+                dev            INTEGER,
+                inode          INTEGER,
+                nlink          INTEGER NOT NULL DEFAULT 1,
                 fast_hash      TEXT,
                 full_hash      TEXT,
+                link_target    TEXT,
                 UNIQUE(directory_id, name)
             );
 
@@ -82,29 +336,104 @@ impl Db {
 
             CREATE TABLE IF NOT EXISTS rules (
                 id       INTEGER PRIMARY KEY,
+                kind     TEXT NOT NULL DEFAULT 'glob',
                 pattern  TEXT NOT NULL,
-                priority INTEGER NOT NULL DEFAULT 0
+                priority INTEGER NOT NULL DEFAULT 0,
+                scope       TEXT,
+                scope_match TEXT NOT NULL DEFAULT 'all',
+                keep        INTEGER NOT NULL DEFAULT 1
             );
+
+            CREATE TABLE IF NOT EXISTS protected (
+                id      INTEGER PRIMARY KEY,
+                pattern TEXT NOT NULL UNIQUE
+            );
+
+            CREATE TABLE IF NOT EXISTS sessions (
+                id         INTEGER PRIMARY KEY,
+                command    TEXT NOT NULL,
+                started_at INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS quarantine (
+                id              INTEGER PRIMARY KEY,
+                session_id      INTEGER NOT NULL REFERENCES sessions(id),
+                original_path   TEXT NOT NULL,
+                quarantine_root TEXT NOT NULL,
+                quarantine_path TEXT NOT NULL UNIQUE,
+                size            INTEGER NOT NULL,
+                modified_at     INTEGER NOT NULL,
+                fast_hash       TEXT,
+                full_hash       TEXT,
+                quarantined_at  INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS deletions (
+                id         INTEGER PRIMARY KEY,
+                session_id INTEGER NOT NULL REFERENCES sessions(id),
+                path       TEXT NOT NULL,
+                size       INTEGER NOT NULL,
+                dev        INTEGER,
+                inode      INTEGER,
+                full_hash  TEXT,
+                kept_path  TEXT NOT NULL,
+                rule       TEXT,
+                action     TEXT NOT NULL,
+                dry_run    INTEGER NOT NULL,
+                deleted_at INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_deletions_session ON deletions(session_id);
+            CREATE INDEX IF NOT EXISTS idx_quarantine_original ON quarantine(original_path);
+            CREATE INDEX IF NOT EXISTS idx_quarantine_full_hash ON quarantine(full_hash);
             ",
         )?;
         Ok(())
     }
 
+    /// Bring databases created by older versions up to the current schema.
+    fn migrate(&self) -> Result<()> {
+        self.add_column_if_missing("files", "dev", "INTEGER")?;
+        self.add_column_if_missing("files", "inode", "INTEGER")?;
+        self.add_column_if_missing("files", "nlink", "INTEGER NOT NULL DEFAULT 1")?;
+        self.add_column_if_missing("files", "link_target", "TEXT")?;
+        self.add_column_if_missing("rules", "kind", "TEXT NOT NULL DEFAULT 'glob'")?;
+        self.add_column_if_missing("rules", "scope", "TEXT")?;
+        self.add_column_if_missing("rules", "scope_match", "TEXT NOT NULL DEFAULT 'all'")?;
+        self.add_column_if_missing("rules", "keep", "INTEGER NOT NULL DEFAULT 1")?;
+        self.add_column_if_missing("directories", "complete", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("deletions", "dev", "INTEGER")?;
+        self.add_column_if_missing("deletions", "inode", "INTEGER")?;
+        self.conn
+            .execute_batch("CREATE INDEX IF NOT EXISTS idx_files_inode ON files(dev, inode);")?;
+        Ok(())
+    }
+
+    fn add_column_if_missing(&self, table: &str, column: &str, decl: &str) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({table})"))?;
+        let exists = stmt
+            .query_map([], |r| r.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?
+            .iter()
+            .any(|c| c == column);
+        if !exists {
+            self.conn
+                .execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl};"))?;
+        }
+        Ok(())
+    }
+
     // ── Directories ──────────────────────────────────────────────────────────
 
     pub fn get_directory(&self, canonical_path: &str) -> Result<Option<DirectoryRow>> {
         let mut stmt = self.conn.prepare_cached(
-            "SELECT id, canonical_path, last_scanned FROM directories WHERE canonical_path = ?1",
+            "SELECT id, canonical_path, last_scanned, parent_id, complete
+             FROM directories WHERE canonical_path = ?1",
         )?;
         let mut rows = stmt.query(params![canonical_path])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(DirectoryRow {
-                id: row.get(0)?,
-                canonical_path: row.get(1)?,
-                last_scanned: row.get(2)?,
-            }))
-        } else {
-            Ok(None)
+        match rows.next()? {
+            Some(row) => Ok(Some(directory_from_row(row)?)),
+            None => Ok(None),
         }
     }
 
@@ -114,23 +443,31 @@ impl Db {
             .parent()
             .and_then(|p| p.to_str())
             .map(str::to_owned);
-        self.conn.execute(
-            "INSERT OR IGNORE INTO directories(canonical_path, parent_id)
-             VALUES(?1, (SELECT id FROM directories WHERE canonical_path = ?2))",
-            params![canonical_path, parent_path],
-        )?;
-        let id: i64 = self.conn.query_row(
-            "SELECT id FROM directories WHERE canonical_path = ?1",
-            params![canonical_path],
-            |r| r.get(0),
+        // The update makes RETURNING yield the id of an existing row too, and
+        // links a directory scanned before its parent to the parent.
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO directories(canonical_path, parent_id)
+             VALUES(?1, (SELECT id FROM directories WHERE canonical_path = ?2))
+             ON CONFLICT(canonical_path) DO UPDATE
+             SET parent_id = COALESCE(parent_id, excluded.parent_id)
+             RETURNING id",
         )?;
+        let id: i64 = stmt.query_row(params![canonical_path, parent_path], |r| r.get(0))?;
         Ok(id)
     }
 
-    pub fn set_directory_scanned(&self, id: i64, timestamp: i64) -> Result<()> {
+    pub fn set_directory_scanned(&self, id: i64, timestamp: i64, complete: bool) -> Result<()> {
         self.conn.execute(
-            "UPDATE directories SET last_scanned = ?1 WHERE id = ?2",
-            params![timestamp, id],
+            "UPDATE directories SET last_scanned = ?1, complete = ?2 WHERE id = ?3",
+            params![timestamp, complete, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_directory_incomplete(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE directories SET complete = 0 WHERE id = ?1",
+            params![id],
         )?;
         Ok(())
     }
@@ -152,17 +489,24 @@ impl Db {
 
     pub fn child_directories(&self, parent_path: &str) -> Result<Vec<DirectoryRow>> {
         let mut stmt = self.conn.prepare_cached(
-            "SELECT id, canonical_path, last_scanned FROM directories
+            "SELECT id, canonical_path, last_scanned, parent_id, complete FROM directories
              WHERE parent_id = (SELECT id FROM directories WHERE canonical_path = ?1)",
         )?;
         let rows = stmt
-            .query_map(params![parent_path.trim_end_matches('/')], |r| {
-                Ok(DirectoryRow {
-                    id: r.get(0)?,
-                    canonical_path: r.get(1)?,
-                    last_scanned: r.get(2)?,
-                })
-            })?
+            .query_map(
+                params![parent_path.trim_end_matches('/')],
+                directory_from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn all_directories(&self) -> Result<Vec<DirectoryRow>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, canonical_path, last_scanned, parent_id, complete FROM directories",
+        )?;
+        let rows = stmt
+            .query_map([], directory_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(rows)
     }
@@ -170,80 +514,241 @@ impl Db {
     // ── Files ────────────────────────────────────────────────────────────────
 
     pub fn files_in_directory(&self, directory_id: i64) -> Result<Vec<FileRow>> {
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT id, directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
              FROM files WHERE directory_id = ?1",
-        )?;
+        ))?;
         let rows = stmt
             .query_map(params![directory_id], file_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(rows)
     }
 
+    pub fn all_files(&self) -> Result<Vec<FileRow>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("SELECT {FILE_COLUMNS} FROM files"))?;
+        let rows = stmt
+            .query_map([], file_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Every file in the directory `path` and its subdirectories.
+    pub fn files_under(&self, path: &str) -> Result<Vec<FileRow>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM directories WHERE canonical_path = ?1
+                 UNION ALL
+                 SELECT d.id FROM directories d JOIN subtree s ON d.parent_id = s.id
+             )
+             SELECT {FILE_COLUMNS}
+             FROM files WHERE directory_id IN (SELECT id FROM subtree)
+             ORDER BY canonical_path",
+        ))?;
+        let rows = stmt
+            .query_map(params![path.trim_end_matches('/')], file_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    pub fn file_by_path(&self, canonical_path: &str) -> Result<Option<FileRow>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
+             FROM files WHERE canonical_path = ?1",
+        ))?;
+        let mut rows = stmt.query(params![canonical_path])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(file_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
     pub fn upsert_file(
         &self,
         directory_id: i64,
         name: &str,
         canonical_path: &str,
-        size: i64,
-        modified_at: i64,
+        meta: &FileMeta,
         fast_hash: Option<&str>,
         full_hash: Option<&str>,
     ) -> Result<i64> {
-        self.conn.execute(
-            "INSERT INTO files(directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash)
-             VALUES(?1,?2,?3,?4,?5,?6,?7)
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO files(directory_id, name, canonical_path, size, modified_at,
+                               dev, inode, nlink, fast_hash, full_hash)
+             VALUES(?1,?2,?3,?4,?5,?6,?7,?8,?9,?10)
              ON CONFLICT(canonical_path) DO UPDATE SET
                directory_id = excluded.directory_id,
                name         = excluded.name,
                size         = excluded.size,
                modified_at  = excluded.modified_at,
+               dev          = excluded.dev,
+               inode        = excluded.inode,
+               nlink        = excluded.nlink,
                fast_hash    = excluded.fast_hash,
-               full_hash    = excluded.full_hash",
-            params![directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash],
+               full_hash    = excluded.full_hash,
+               link_target  = NULL
+             RETURNING id",
         )?;
-        let id: i64 = self.conn.query_row(
-            "SELECT id FROM files WHERE canonical_path = ?1",
-            params![canonical_path],
+        let id: i64 = stmt.query_row(
+            params![
+                directory_id,
+                name,
+                canonical_path,
+                meta.size,
+                meta.modified_at,
+                meta.dev,
+                meta.inode,
+                meta.nlink,
+                fast_hash,
+                full_hash
+            ],
             |r| r.get(0),
         )?;
         Ok(id)
     }
 
-    pub fn update_fast_hash(&self, id: i64, fast_hash: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE files SET fast_hash = ?1, full_hash = NULL WHERE id = ?2",
-            params![fast_hash, id],
+    /// Insert or update a symbolic link entry. Links carry no hashes, so they
+    /// never take part in duplicate groups.
+    pub fn upsert_symlink(
+        &self,
+        directory_id: i64,
+        name: &str,
+        canonical_path: &str,
+        meta: &FileMeta,
+        link_target: &str,
+    ) -> Result<i64> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO files(directory_id, name, canonical_path, size, modified_at,
+                               dev, inode, nlink, link_target)
+             VALUES(?1,?2,?3,?4,?5,?6,?7,?8,?9)
+             ON CONFLICT(canonical_path) DO UPDATE SET
+               directory_id = excluded.directory_id,
+               name         = excluded.name,
+               size         = excluded.size,
+               modified_at  = excluded.modified_at,
+               dev          = excluded.dev,
+               inode        = excluded.inode,
+               nlink        = excluded.nlink,
+               fast_hash    = NULL,
+               full_hash    = NULL,
+               link_target  = excluded.link_target
+             RETURNING id",
+        )?;
+        let id: i64 = stmt.query_row(
+            params![
+                directory_id,
+                name,
+                canonical_path,
+                meta.size,
+                meta.modified_at,
+                meta.dev,
+                meta.inode,
+                meta.nlink,
+                link_target
+            ],
+            |r| r.get(0),
         )?;
+        Ok(id)
+    }
+
+    /// Turn a file row into a symlink entry after the file was replaced by a
+    /// link to another copy.
+    pub fn mark_symlink(&self, id: i64, meta: &FileMeta, link_target: &str) -> Result<()> {
+        self.conn
+            .prepare_cached(
+                "UPDATE files SET size = ?1, modified_at = ?2, dev = ?3, inode = ?4, nlink = ?5,
+                                  fast_hash = NULL, full_hash = NULL, link_target = ?6
+                 WHERE id = ?7",
+            )?
+            .execute(params![
+                meta.size,
+                meta.modified_at,
+                meta.dev,
+                meta.inode,
+                meta.nlink,
+                link_target,
+                id
+            ])?;
         Ok(())
     }
 
-    pub fn update_full_hash(&self, id: i64, full_hash: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE files SET full_hash = ?1 WHERE id = ?2",
-            params![full_hash, id],
-        )?;
+    /// Refresh the inode columns of an otherwise unchanged file.
+    pub fn update_inode(&self, id: i64, meta: &FileMeta) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE files SET dev = ?1, inode = ?2, nlink = ?3 WHERE id = ?4")?
+            .execute(params![meta.dev, meta.inode, meta.nlink, id])?;
         Ok(())
     }
 
-    pub fn delete_file(&self, id: i64) -> Result<()> {
+    /// Replace the stat data of a file whose content is unchanged, e.g. after
+    /// it was swapped for a hard link to an identical file.
+    pub fn update_file_meta(&self, id: i64, meta: &FileMeta) -> Result<()> {
         self.conn
-            .execute("DELETE FROM files WHERE id = ?1", params![id])?;
+            .prepare_cached(
+                "UPDATE files SET size = ?1, modified_at = ?2, dev = ?3, inode = ?4, nlink = ?5
+                 WHERE id = ?6",
+            )?
+            .execute(params![meta.size, meta.modified_at, meta.dev, meta.inode, meta.nlink, id])?;
         Ok(())
     }
 
-    pub fn delete_file_by_path(&self, canonical_path: &str) -> Result<()> {
-        self.conn.execute(
-            "DELETE FROM files WHERE canonical_path = ?1",
-            params![canonical_path],
+    /// Set the link count on every row that is a link to the given inode.
+    pub fn set_nlink(&self, dev: i64, inode: i64, nlink: i64) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE files SET nlink = ?1 WHERE dev = ?2 AND inode = ?3")?
+            .execute(params![nlink, dev, inode])?;
+        Ok(())
+    }
+
+    /// One link to the given inode is gone: lower the link count of the rows
+    /// of the links left.
+    pub fn drop_link(&self, dev: i64, inode: i64) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE files SET nlink = nlink - 1 WHERE dev = ?1 AND inode = ?2")?
+            .execute(params![dev, inode])?;
+        Ok(())
+    }
+
+    /// A hashed file with the given inode and stat data, if any: another hard
+    /// link whose hashes can be reused instead of reading the data again.
+    pub fn hashed_link(&self, meta: &FileMeta) -> Result<Option<FileRow>> {
+        let (Some(dev), Some(inode)) = (meta.dev, meta.inode) else {
+            return Ok(None);
+        };
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
+             FROM files
+             WHERE dev = ?1 AND inode = ?2 AND size = ?3 AND modified_at = ?4
+               AND fast_hash IS NOT NULL
+             ORDER BY full_hash IS NULL
+             LIMIT 1",
+        ))?;
+        let mut rows = stmt.query_map(
+            params![dev, inode, meta.size, meta.modified_at],
+            file_from_row,
         )?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn update_full_hash(&self, id: i64, full_hash: &str) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE files SET full_hash = ?1 WHERE id = ?2")?
+            .execute(params![full_hash, id])?;
+        Ok(())
+    }
+
+    pub fn delete_file(&self, id: i64) -> Result<()> {
+        self.conn
+            .prepare_cached("DELETE FROM files WHERE id = ?1")?
+            .execute(params![id])?;
         Ok(())
     }
 
     /// Find files that share the same (size, fast_hash) and are missing a full_hash.
     pub fn candidates_needing_full_hash(&self) -> Result<Vec<FileRow>> {
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT id, directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
              FROM files
              WHERE full_hash IS NULL
                AND fast_hash IS NOT NULL
@@ -254,7 +759,45 @@ impl Db {
                    GROUP BY size, fast_hash
                    HAVING COUNT(*) > 1
                )",
+        ))?;
+        let rows = stmt
+            .query_map([], file_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Like `candidates_needing_full_hash`, but only for collision groups that
+    /// have at least one file directly in one of `directory_ids`.
+    pub fn candidates_needing_full_hash_in(&self, directory_ids: &[i64]) -> Result<Vec<FileRow>> {
+        self.conn.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS scan_scope(directory_id INTEGER PRIMARY KEY);
+             DELETE FROM temp.scan_scope;",
         )?;
+        {
+            let mut insert = self
+                .conn
+                .prepare_cached("INSERT OR IGNORE INTO temp.scan_scope(directory_id) VALUES(?1)")?;
+            for id in directory_ids {
+                insert.execute(params![id])?;
+            }
+        }
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
+             FROM files
+             WHERE full_hash IS NULL
+               AND fast_hash IS NOT NULL
+               AND size > 0
+               AND (size, fast_hash) IN (
+                   SELECT size, fast_hash FROM files
+                   WHERE fast_hash IS NOT NULL
+                   GROUP BY size, fast_hash
+                   HAVING COUNT(*) > 1
+               )
+               AND (size, fast_hash) IN (
+                   SELECT size, fast_hash FROM files
+                   WHERE directory_id IN (SELECT directory_id FROM temp.scan_scope)
+               )",
+        ))?;
         let rows = stmt
             .query_map([], file_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
@@ -282,31 +825,47 @@ impl Db {
     }
 
     pub fn files_with_full_hash(&self, full_hash: &str) -> Result<Vec<FileRow>> {
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT id, directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
              FROM files WHERE full_hash = ?1",
-        )?;
+        ))?;
         let rows = stmt
             .query_map(params![full_hash], file_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(rows)
     }
 
-    /// Count and total size of duplicate files under (and including) the given path prefix.
+    /// Copies under (and including) the given path prefix that can go while one
+    /// copy of each duplicated file is kept, here or elsewhere, and their size.
+    /// For each group that is `min(copies here, copies in all - 1)`: all of them
+    /// when a copy lies outside the prefix, all but one otherwise. Hard links
+    /// to one inode count as one copy, so groups that are only hard links count
+    /// nothing.
     pub fn duplicate_stats_under(&self, path_prefix: &str) -> Result<(i64, i64)> {
         let (count, size): (i64, i64) = self.conn.query_row(
-            "WITH RECURSIVE subtree(id) AS (
-                 SELECT id FROM directories WHERE canonical_path = ?1
-                 UNION ALL
-                 SELECT d.id FROM directories d JOIN subtree s ON d.parent_id = s.id
-             )
-             SELECT COUNT(*), COALESCE(SUM(size), 0) FROM files
-             WHERE directory_id IN (SELECT id FROM subtree)
-               AND full_hash IS NOT NULL
-               AND full_hash IN (
-                   SELECT full_hash FROM files WHERE full_hash IS NOT NULL
-                   GROUP BY full_hash HAVING COUNT(*) > 1
-               )",
+            &format!(
+                "WITH RECURSIVE subtree(id) AS (
+                     SELECT id FROM directories WHERE canonical_path = ?1
+                     UNION ALL
+                     SELECT d.id FROM directories d JOIN subtree s ON d.parent_id = s.id
+                 ),
+                 copies AS (
+                     SELECT DISTINCT full_hash, {INODE_KEY} AS copy, size FROM files
+                     WHERE directory_id IN (SELECT id FROM subtree)
+                       AND full_hash IS NOT NULL
+                 ),
+                 totals AS (
+                     SELECT full_hash, COUNT(DISTINCT {INODE_KEY}) AS total FROM files
+                     WHERE full_hash IS NOT NULL
+                     GROUP BY full_hash HAVING total > 1
+                 ),
+                 reclaimable AS (
+                     SELECT MIN(COUNT(*), t.total - 1) AS n, MAX(c.size) AS size
+                     FROM copies c JOIN totals t ON t.full_hash = c.full_hash
+                     GROUP BY c.full_hash
+                 )
+                 SELECT COALESCE(SUM(n), 0), COALESCE(SUM(n * size), 0) FROM reclaimable",
+            ),
             params![path_prefix.trim_end_matches('/')],
             |r| Ok((r.get(0)?, r.get(1)?)),
         )?;
@@ -315,16 +874,16 @@ impl Db {
 
     /// Duplicate files directly in this directory (not subdirs).
     pub fn duplicate_files_in_dir(&self, directory_id: i64) -> Result<Vec<FileRow>> {
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT id, directory_id, name, canonical_path, size, modified_at, fast_hash, full_hash
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {FILE_COLUMNS}
              FROM files
              WHERE directory_id = ?1
                AND full_hash IS NOT NULL
                AND full_hash IN (
                    SELECT full_hash FROM files WHERE full_hash IS NOT NULL
-                   GROUP BY full_hash HAVING COUNT(*) > 1
+                   GROUP BY full_hash HAVING COUNT(DISTINCT {INODE_KEY}) > 1
                )",
-        )?;
+        ))?;
         let rows = stmt
             .query_map(params![directory_id], file_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
@@ -333,33 +892,300 @@ impl Db {
 
     // ── Rules ────────────────────────────────────────────────────────────────
 
+    /// All rules in the order they are applied: highest priority first,
+    /// equal priorities oldest first.
     pub fn all_rules(&self) -> Result<Vec<RuleRow>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, kind, pattern, priority, scope, scope_match, keep FROM rules
+             ORDER BY priority DESC, id",
+        )?;
+        let rows = stmt
+            .query_map([], rule_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Insert `rule` (its id is ignored); returns the new id.
+    pub fn insert_rule(&self, rule: &RuleRow) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO rules(kind, pattern, priority, scope, scope_match, keep)
+             VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                rule.kind.name(),
+                rule.pattern,
+                rule.priority,
+                rule.scope,
+                rule.scope_match.name(),
+                rule.keep
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn get_rule(&self, id: i64) -> Result<Option<RuleRow>> {
         let mut stmt = self
             .conn
-            .prepare_cached("SELECT id, pattern, priority FROM rules ORDER BY priority DESC")?;
+            .prepare_cached(
+                "SELECT id, kind, pattern, priority, scope, scope_match, keep FROM rules
+                 WHERE id = ?1",
+            )?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(rule_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns false when there is no rule `id`.
+    pub fn update_rule(&self, rule: &RuleRow) -> Result<bool> {
+        let n = self.conn.execute(
+            "UPDATE rules
+             SET kind = ?2, pattern = ?3, priority = ?4, scope = ?5, scope_match = ?6,
+                 keep = ?7
+             WHERE id = ?1",
+            params![
+                rule.id,
+                rule.kind.name(),
+                rule.pattern,
+                rule.priority,
+                rule.scope,
+                rule.scope_match.name(),
+                rule.keep
+            ],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Returns false when there is no rule `id`.
+    pub fn delete_rule(&self, id: i64) -> Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM rules WHERE id = ?1", params![id])?;
+        Ok(n > 0)
+    }
+
+    /// Delete every rule; returns how many there were.
+    pub fn delete_all_rules(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM rules", [])?)
+    }
+
+    // ── Protected paths ──────────────────────────────────────────────────────
+
+    pub fn protected_patterns(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT pattern FROM protected ORDER BY id")?;
+        let rows = stmt
+            .query_map([], |r| r.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Returns false when `pattern` was already protected.
+    pub fn insert_protected(&self, pattern: &str) -> Result<bool> {
+        let n = self.conn.execute(
+            "INSERT OR IGNORE INTO protected(pattern) VALUES(?1)",
+            params![pattern],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// Returns false when `pattern` was not protected.
+    pub fn delete_protected(&self, pattern: &str) -> Result<bool> {
+        let n = self
+            .conn
+            .execute("DELETE FROM protected WHERE pattern = ?1", params![pattern])?;
+        Ok(n > 0)
+    }
+
+    // ── Sessions / quarantine ────────────────────────────────────────────────
+
+    /// Record the start of a `remove` (or similar) run; returns its id.
+    pub fn start_session(&self, command: &str, started_at: i64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO sessions(command, started_at) VALUES(?1, ?2)",
+            params![command, started_at],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Append to the `deletions` audit log; `id` is ignored.
+    pub fn record_deletion(&self, row: &DeletionRow) -> Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO deletions(session_id, path, size, dev, inode, full_hash, kept_path,
+                                       rule, action, dry_run, deleted_at)
+                 VALUES(?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)",
+            )?
+            .execute(params![
+                row.session_id,
+                row.path,
+                row.size,
+                row.dev,
+                row.inode,
+                row.full_hash,
+                row.kept_path,
+                row.rule,
+                row.action,
+                row.dry_run,
+                row.deleted_at
+            ])?;
+        Ok(())
+    }
+
+    /// Sessions that acted on at least one file, newest first. A session
+    /// counts as a dry run if all its entries are. Entries for paths to one
+    /// inode add its size once; entries without an inode each add theirs.
+    pub fn session_summaries(&self) -> Result<Vec<SessionSummary>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT s.id, s.command, s.started_at, COUNT(*),
+                    (SELECT COALESCE(SUM(size), 0) FROM (
+                         SELECT MAX(i.size) AS size FROM deletions i
+                         WHERE i.session_id = s.id
+                         GROUP BY i.dev, i.inode, CASE WHEN i.inode IS NULL THEN i.id END
+                     )),
+                    MIN(d.dry_run)
+             FROM sessions s JOIN deletions d ON d.session_id = s.id
+             GROUP BY s.id
+             ORDER BY s.id DESC",
+        )?;
         let rows = stmt
             .query_map([], |r| {
-                Ok(RuleRow {
+                Ok(SessionSummary {
+                    id: r.get(0)?,
+                    command: r.get(1)?,
+                    started_at: r.get(2)?,
+                    files: r.get(3)?,
+                    bytes: r.get(4)?,
+                    dry_run: r.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    /// Audit log entries of one session, or of every session if None.
+    pub fn deletions(&self, session_id: Option<i64>) -> Result<Vec<DeletionRow>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, session_id, path, size, dev, inode, full_hash, kept_path, rule, action,
+                    dry_run, deleted_at
+             FROM deletions
+             WHERE ?1 IS NULL OR session_id = ?1
+             ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![session_id], |r| {
+                Ok(DeletionRow {
                     id: r.get(0)?,
-                    pattern: r.get(1)?,
-                    priority: r.get(2)?,
+                    session_id: r.get(1)?,
+                    path: r.get(2)?,
+                    size: r.get(3)?,
+                    dev: r.get(4)?,
+                    inode: r.get(5)?,
+                    full_hash: r.get(6)?,
+                    kept_path: r.get(7)?,
+                    rule: r.get(8)?,
+                    action: r.get(9)?,
+                    dry_run: r.get(10)?,
+                    deleted_at: r.get(11)?,
                 })
             })?
             .collect::<rusqlite::Result<Vec<_>>>()?;
         Ok(rows)
     }
 
-    pub fn insert_rule(&self, pattern: &str, priority: i64) -> Result<()> {
+    /// Record a file moved into quarantine; `id` is ignored.
+    pub fn insert_quarantine(&self, row: &QuarantineRow) -> Result<i64> {
         self.conn.execute(
-            "INSERT INTO rules(pattern, priority) VALUES(?1, ?2)",
-            params![pattern, priority],
+            "INSERT INTO quarantine(session_id, original_path, quarantine_root, quarantine_path,
+                                    size, modified_at, fast_hash, full_hash, quarantined_at)
+             VALUES(?1,?2,?3,?4,?5,?6,?7,?8,?9)",
+            params![
+                row.session_id,
+                row.original_path,
+                row.quarantine_root,
+                row.quarantine_path,
+                row.size,
+                row.modified_at,
+                row.fast_hash,
+                row.full_hash,
+                row.quarantined_at
+            ],
         )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Quarantined files whose original path is `path` or lies under it.
+    pub fn quarantined_under(&self, path: &str) -> Result<Vec<QuarantineRow>> {
+        let path = path.trim_end_matches('/');
+        self.quarantine_where(
+            "original_path = ?1 OR substr(original_path, 1, length(?1) + 1) = ?1 || '/'",
+            params![path],
+        )
+    }
+
+    /// Quarantined copies of the duplicate group with this full hash.
+    pub fn quarantined_with_hash(&self, full_hash: &str) -> Result<Vec<QuarantineRow>> {
+        self.quarantine_where("full_hash = ?1", params![full_hash])
+    }
+
+    pub fn quarantined_in_session(&self, session_id: i64) -> Result<Vec<QuarantineRow>> {
+        self.quarantine_where("session_id = ?1", params![session_id])
+    }
+
+    /// Files quarantined before the given Unix timestamp.
+    pub fn quarantined_before(&self, timestamp: i64) -> Result<Vec<QuarantineRow>> {
+        self.quarantine_where("quarantined_at < ?1", params![timestamp])
+    }
+
+    pub fn delete_quarantine(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM quarantine WHERE id = ?1", params![id])?;
         Ok(())
     }
+
+    fn quarantine_where(
+        &self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<QuarantineRow>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT id, session_id, original_path, quarantine_root, quarantine_path,
+                    size, modified_at, fast_hash, full_hash, quarantined_at
+             FROM quarantine WHERE {condition} ORDER BY id",
+        ))?;
+        let rows = stmt
+            .query_map(params, |r| {
+                Ok(QuarantineRow {
+                    id: r.get(0)?,
+                    session_id: r.get(1)?,
+                    original_path: r.get(2)?,
+                    quarantine_root: r.get(3)?,
+                    quarantine_path: r.get(4)?,
+                    size: r.get(5)?,
+                    modified_at: r.get(6)?,
+                    fast_hash: r.get(7)?,
+                    full_hash: r.get(8)?,
+                    quarantined_at: r.get(9)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
 }
 
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
+fn directory_from_row(r: &rusqlite::Row) -> rusqlite::Result<DirectoryRow> {
+    Ok(DirectoryRow {
+        id: r.get(0)?,
+        canonical_path: r.get(1)?,
+        last_scanned: r.get(2)?,
+        parent_id: r.get(3)?,
+        complete: r.get(4)?,
+    })
+}
+
 fn file_from_row(r: &rusqlite::Row) -> rusqlite::Result<FileRow> {
     Ok(FileRow {
         id: r.get(0)?,
@@ -368,8 +1194,39 @@ fn file_from_row(r: &rusqlite::Row) -> rusqlite::Result<FileRow> {
         canonical_path: r.get(3)?,
         size: r.get(4)?,
         modified_at: r.get(5)?,
-        fast_hash: r.get(6)?,
-        full_hash: r.get(7)?,
+        dev: r.get(6)?,
+        inode: r.get(7)?,
+        nlink: r.get(8)?,
+        fast_hash: r.get(9)?,
+        full_hash: r.get(10)?,
+        link_target: r.get(11)?,
     })
 }
 
+fn rule_from_row(r: &rusqlite::Row) -> rusqlite::Result<RuleRow> {
+    let kind: String = r.get(1)?;
+    Ok(RuleRow {
+        id: r.get(0)?,
+        kind: RuleKind::from_name(&kind).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                1,
+                rusqlite::types::Type::Text,
+                format!("unknown rule kind '{}'", kind).into(),
+            )
+        })?,
+        pattern: r.get(2)?,
+        priority: r.get(3)?,
+        scope: r.get(4)?,
+        scope_match: {
+            let name: String = r.get(5)?;
+            ScopeMatch::from_name(&name).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    5,
+                    rusqlite::types::Type::Text,
+                    format!("unknown scope match '{}'", name).into(),
+                )
+            })?
+        },
+        keep: r.get(6)?,
+    })
+}
@@ -0,0 +1,283 @@
+use anyhow::{bail, Result};
+use std::io;
+
+use crate::action::{self, ActionOptions};
+use crate::cli::{DedupeArgs, DedupeFallback, RemoveAction, SymlinkTarget};
+use crate::config::Config;
+use crate::db::{Db, FileRow};
+use crate::link;
+use crate::protect::Protected;
+use crate::rules;
+use crate::tui::fmt_size;
+use crate::verify::KeptFileChanged;
+
+/// Why the extents of a duplicate could not be shared with the kept copy.
+enum ShareError {
+    /// The filesystem has no extent sharing, or the files are on different
+    /// filesystems.
+    Unsupported(io::Error),
+    /// The kernel compared the ranges and found different data.
+    Differs,
+    /// The kernel stopped sharing before the end of the file: only the
+    /// first `shared` of `len` bytes share extents.
+    Partial {
+        shared: u64,
+        len: u64,
+    },
+    Failed(io::Error),
+}
+
+pub fn run(args: &DedupeArgs, config: &Config, db: &Db) -> Result<()> {
+    if !args.reflink {
+        bail!("no dedupe mode given; use --reflink");
+    }
+
+    let protected = Protected::load(config, db)?;
+    let (covered, groups): (Vec<_>, Vec<_>) = db
+        .duplicate_groups()?
+        .into_iter()
+        .filter(|g| !g.is_hard_linked())
+        .partition(|g| protected.covers(g));
+    if groups.is_empty() {
+        if covered.is_empty() {
+            println!("No duplicates found. Run 'fdedupe scan' first.");
+        }
+        link::report_protected(&covered);
+        return Ok(());
+    }
+
+    let rules = rules::load(config, db)?;
+    let hardlink = ActionOptions {
+        dry_run: args.dry_run,
+        session_id: action::start_session(db)?,
+        action: RemoveAction::Hardlink,
+        symlink_target: SymlinkTarget::default(),
+        quarantine: None,
+        paranoid: false,
+        protected,
+    };
+    let verb = if args.dry_run {
+        "Would share"
+    } else {
+        "Shared"
+    };
+    let mut shared_total = 0;
+    let mut unsupported = 0;
+    let mut linked = 0;
+    let mut failed = 0;
+
+    for group in &groups {
+        let (keep_idx, rule) = link::link_source(group, &rules);
+        let keep = &group.files[keep_idx];
+        println!();
+        println!("Keep: {}  ({})", keep.canonical_path, fmt_size(keep.size));
+
+        let mut shared = 0;
+        for (i, target) in group.files.iter().enumerate() {
+            if i == keep_idx || target.shares_inode_with(keep) {
+                continue;
+            }
+            if hardlink.protected.contains(&target.canonical_path) {
+                println!("  protected {}", target.canonical_path);
+                continue;
+            }
+            if args.dry_run {
+                println!("  would share {}", target.canonical_path);
+                action::record(&hardlink, keep, target, rule.as_deref(), "reflink", db)?;
+                shared += target.size as u64;
+                continue;
+            }
+            match share_extents(keep, target) {
+                Ok(n) => {
+                    println!("  shared {}  {}", fmt_size(n as i64), target.canonical_path);
+                    action::record(&hardlink, keep, target, rule.as_deref(), "reflink", db)?;
+                    shared += n;
+                }
+                Err(ShareError::Unsupported(e)) => match args.fallback {
+                    DedupeFallback::None => {
+                        println!("  not supported: {} ({})", target.canonical_path, e);
+                        unsupported += 1;
+                    }
+                    DedupeFallback::Hardlink => {
+                        match action::apply(&hardlink, keep, target, rule.as_deref(), db) {
+                            Ok(()) => {
+                                println!("  hard-linked {} ({})", target.canonical_path, e);
+                                linked += 1;
+                            }
+                            Err(e) => {
+                                eprintln!("  failed: {:#}", e);
+                                failed += 1;
+                                if e.is::<KeptFileChanged>() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                },
+                Err(ShareError::Differs) => {
+                    eprintln!(
+                        "  skipped {}: content differs from the kept copy (changed since scan?)",
+                        target.canonical_path
+                    );
+                    failed += 1;
+                }
+                Err(ShareError::Partial { shared, len }) => {
+                    eprintln!(
+                        "  failed: {}: the kernel shared only {} of {}",
+                        target.canonical_path,
+                        fmt_size(shared as i64),
+                        fmt_size(len as i64)
+                    );
+                    failed += 1;
+                }
+                Err(ShareError::Failed(e)) => {
+                    eprintln!("  failed: {}: {}", target.canonical_path, e);
+                    failed += 1;
+                }
+            }
+        }
+        println!(
+            "  {} {} in this group",
+            verb.to_lowercase(),
+            fmt_size(shared as i64)
+        );
+        shared_total += shared;
+    }
+
+    println!();
+    println!(
+        "{} {} in {} groups",
+        verb,
+        fmt_size(shared_total as i64),
+        groups.len()
+    );
+    if linked > 0 {
+        println!(
+            "Hard-linked {} files on filesystems without extent sharing",
+            linked
+        );
+    }
+    if unsupported > 0 {
+        println!(
+            "{} files are on filesystems without extent sharing (btrfs, XFS and similar \
+             support it); use --fallback hardlink to link them instead",
+            unsupported
+        );
+    }
+    if failed > 0 {
+        println!("{} files failed", failed);
+    }
+    link::report_protected(&covered);
+    Ok(())
+}
+
+// ── FIDEDUPERANGE ────────────────────────────────────────────────────────────
+
+/// `_IOWR(0x94, 54, struct file_dedupe_range)` from `linux/fs.h`.
+#[cfg(target_os = "linux")]
+const FIDEDUPERANGE: u64 = 0xC018_9436;
+
+/// Largest range passed to one ioctl; filesystems cap a single request at
+/// 16 MiB and report less than asked for beyond that.
+#[cfg(target_os = "linux")]
+const CHUNK: u64 = 16 * 1024 * 1024;
+
+/// `struct file_dedupe_range` with a single destination.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct DedupeRange {
+    src_offset: u64,
+    src_length: u64,
+    dest_count: u16,
+    reserved1: u16,
+    reserved2: u32,
+    info: DedupeRangeInfo,
+}
+
+/// `struct file_dedupe_range_info`.
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct DedupeRangeInfo {
+    dest_fd: i64,
+    dest_offset: u64,
+    bytes_deduped: u64,
+    /// 0 = same, 1 = differs, negative errno on failure.
+    status: i32,
+    reserved: u32,
+}
+
+/// Ask the kernel to make `target` share `keep`'s extents, chunk by chunk.
+/// The kernel locks and compares both ranges itself. `target` is opened for
+/// writing, which the ioctl requires unless the caller owns the file.
+/// Returns the bytes shared, always the whole file: a request the kernel
+/// stops short of fails with `ShareError::Partial`.
+#[cfg(target_os = "linux")]
+fn share_extents(keep: &FileRow, target: &FileRow) -> Result<u64, ShareError> {
+    use std::os::unix::io::AsRawFd;
+
+    let src = std::fs::File::open(&keep.canonical_path).map_err(ShareError::Failed)?;
+    let dest = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&target.canonical_path)
+        .map_err(ShareError::Failed)?;
+    let len = src.metadata().map_err(ShareError::Failed)?.len();
+
+    let mut offset = 0;
+    while offset < len {
+        let mut range = DedupeRange {
+            src_offset: offset,
+            src_length: (len - offset).min(CHUNK),
+            dest_count: 1,
+            reserved1: 0,
+            reserved2: 0,
+            info: DedupeRangeInfo {
+                dest_fd: dest.as_raw_fd() as i64,
+                dest_offset: offset,
+                bytes_deduped: 0,
+                status: 0,
+                reserved: 0,
+            },
+        };
+        // SAFETY: `range` is a valid file_dedupe_range with one info entry,
+        // and both descriptors stay open for the duration of the call.
+        let rc = unsafe { libc::ioctl(src.as_raw_fd(), FIDEDUPERANGE as _, &mut range) };
+        if rc < 0 {
+            return Err(classify(io::Error::last_os_error()));
+        }
+        match range.info.status {
+            0 => {}
+            1 => return Err(ShareError::Differs),
+            errno => return Err(classify(io::Error::from_raw_os_error(-errno))),
+        }
+        if range.info.bytes_deduped == 0 {
+            return Err(ShareError::Partial {
+                shared: offset,
+                len,
+            });
+        }
+        offset += range.info.bytes_deduped;
+    }
+    Ok(offset)
+}
+
+/// Only errors that mean the filesystem cannot share extents at all count
+/// as unsupported; `EINVAL` and `EPERM` concern the file at hand (its
+/// permissions, open mode or type) and are reported as failures.
+#[cfg(target_os = "linux")]
+fn classify(e: io::Error) -> ShareError {
+    match e.raw_os_error() {
+        Some(libc::EOPNOTSUPP | libc::ENOTTY | libc::EXDEV | libc::ENOSYS) => {
+            ShareError::Unsupported(e)
+        }
+        _ => ShareError::Failed(e),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn share_extents(_keep: &FileRow, _target: &FileRow) -> Result<u64, ShareError> {
+    Err(ShareError::Unsupported(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "FIDEDUPERANGE is only available on Linux",
+    )))
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use chrono::TimeZone;
+
+use crate::cli::{HistoryArgs, HistoryFormat};
+use crate::config::Config;
+use crate::db::{Db, DeletionRow};
+use crate::tui::fmt_size;
+
+pub fn run(args: &HistoryArgs, _config: &Config, db: &Db) -> Result<()> {
+    match (args.format, args.session) {
+        (HistoryFormat::Table, None) => print_sessions(db),
+        (HistoryFormat::Table, Some(id)) => print_session(db, id),
+        (HistoryFormat::Csv, session) => {
+            print!("{}", to_csv(&db.deletions(session)?));
+            Ok(())
+        }
+        (HistoryFormat::Json, session) => {
+            println!("{}", serde_json::to_string_pretty(&db.deletions(session)?)?);
+            Ok(())
+        }
+    }
+}
+
+fn print_sessions(db: &Db) -> Result<()> {
+    let sessions = db.session_summaries()?;
+    if sessions.is_empty() {
+        println!("No history yet.");
+        return Ok(());
+    }
+    for s in &sessions {
+        println!(
+            "#{:<5} {}  {:>5} files  {:>10}{}  {}",
+            s.id,
+            local_time(s.started_at),
+            s.files,
+            fmt_size(s.bytes),
+            if s.dry_run { "  [DRY RUN]" } else { "" },
+            s.command
+        );
+    }
+    Ok(())
+}
+
+fn print_session(db: &Db, id: i64) -> Result<()> {
+    let rows = db.deletions(Some(id))?;
+    if rows.is_empty() {
+        println!("Session {} did not act on any file.", id);
+        return Ok(());
+    }
+    for d in &rows {
+        println!(
+            "{} {:<10} {}  ({}){}",
+            local_time(d.deleted_at),
+            if d.dry_run {
+                format!("({})", d.action)
+            } else {
+                d.action.clone()
+            },
+            d.path,
+            fmt_size(d.size),
+            if d.dry_run { "  [DRY RUN]" } else { "" }
+        );
+        println!(
+            "    kept {}  — {}",
+            d.kept_path,
+            d.rule
+                .as_deref()
+                .map_or("chosen by hand".to_owned(), |r| format!("rule {}", r))
+        );
+    }
+    Ok(())
+}
+
+pub fn local_time(timestamp: i64) -> String {
+    chrono::Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .map_or_else(
+            || timestamp.to_string(),
+            |t| t.format("%Y-%m-%d %H:%M:%S").to_string(),
+        )
+}
+
+/// RFC 4180 CSV with a header row; timestamps are Unix seconds.
+fn to_csv(rows: &[DeletionRow]) -> String {
+    let mut out = String::from(
+        "id,session_id,path,size,dev,inode,full_hash,kept_path,rule,action,dry_run,deleted_at\r\n",
+    );
+    for d in rows {
+        let fields = [
+            d.id.to_string(),
+            d.session_id.to_string(),
+            d.path.clone(),
+            d.size.to_string(),
+            d.dev.map(|v| v.to_string()).unwrap_or_default(),
+            d.inode.map(|v| v.to_string()).unwrap_or_default(),
+            d.full_hash.clone().unwrap_or_default(),
+            d.kept_path.clone(),
+            d.rule.clone().unwrap_or_default(),
+            d.action.clone(),
+            d.dry_run.to_string(),
+            d.deleted_at.to_string(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
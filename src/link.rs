@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+use crate::action::{self, ActionOptions};
+use crate::cli::{LinkArgs, RemoveAction, SymlinkTarget};
+use crate::config::Config;
+use crate::db::{Db, DuplicateGroup, RuleRow};
+use crate::protect::Protected;
+use crate::rules;
+use crate::tui::fmt_size;
+use crate::verify::KeptFileChanged;
+
+pub fn run(args: &LinkArgs, config: &Config, db: &Db) -> Result<()> {
+    let protected = Protected::load(config, db)?;
+    let (covered, groups): (Vec<_>, Vec<_>) = db
+        .duplicate_groups()?
+        .into_iter()
+        .filter(|g| !g.is_hard_linked())
+        .partition(|g| protected.covers(g));
+    if groups.is_empty() {
+        if covered.is_empty() {
+            println!("No duplicates found. Run 'fdedupe scan' first.");
+        }
+        report_protected(&covered);
+        return Ok(());
+    }
+
+    let rules = rules::load(config, db)?;
+    let opts = ActionOptions {
+        dry_run: args.dry_run,
+        session_id: action::start_session(db)?,
+        action: RemoveAction::Hardlink,
+        symlink_target: SymlinkTarget::default(),
+        quarantine: None,
+        paranoid: args.paranoid,
+        protected,
+    };
+    let verb = if args.dry_run { "Would link" } else { "Linked" };
+    let mut linked = 0;
+    let mut failed = 0;
+    let mut freed = 0;
+
+    for group in &groups {
+        let (keep_idx, rule) = link_source(group, &rules);
+        let keep = &group.files[keep_idx];
+        let size = keep.size;
+        println!();
+        println!("Keep: {}  ({})", keep.canonical_path, fmt_size(size));
+
+        let mut done = Vec::new();
+        for (i, target) in group.files.iter().enumerate() {
+            if i == keep_idx || target.shares_inode_with(keep) {
+                continue;
+            }
+            if opts.protected.contains(&target.canonical_path) {
+                println!("  protected {}", target.canonical_path);
+                continue;
+            }
+            match action::apply(&opts, keep, target, rule.as_deref(), db) {
+                Ok(()) => {
+                    println!("  {} {}", verb.to_lowercase(), target.canonical_path);
+                    done.push(i);
+                }
+                Err(e) => {
+                    eprintln!("  failed: {:#}", e);
+                    failed += 1;
+                    if e.is::<KeptFileChanged>() {
+                        break;
+                    }
+                }
+            }
+        }
+        linked += done.len();
+        freed += group.bytes_freed(&done);
+    }
+
+    println!();
+    println!(
+        "{} {} files in {} groups, freeing {}{}",
+        verb,
+        linked,
+        groups.len(),
+        fmt_size(freed),
+        if failed > 0 {
+            format!("; {} failed", failed)
+        } else {
+            String::new()
+        }
+    );
+    report_protected(&covered);
+    Ok(())
+}
+
+/// Report the groups skipped because every copy is protected.
+pub fn report_protected(covered: &[DuplicateGroup]) {
+    if !covered.is_empty() {
+        println!(
+            "Skipped {} group(s) where every copy is protected.",
+            covered.len()
+        );
+    }
+}
+
+/// Index of the copy the others get linked to: the (first) one the rules
+/// keep if they decide the group (with the deciding rule), otherwise the
+/// inode that already has most paths.
+pub fn link_source(group: &DuplicateGroup, rules: &[RuleRow]) -> (usize, Option<String>) {
+    if let Some((kept, rule)) = rules::keepers(&group.files, rules) {
+        return (kept[0], Some(rule.describe()));
+    }
+    let links = |i: usize| {
+        let f = &group.files[i];
+        group
+            .files
+            .iter()
+            .filter(|o| o.id == f.id || f.shares_inode_with(o))
+            .count()
+    };
+    let keep = (0..group.files.len())
+        .max_by_key(|&i| (links(i), Reverse(i)))
+        .unwrap_or(0);
+    (keep, None)
+}
+
+/// Atomically replace `target` with a hard link to `keep`: link to a
+/// temporary name next to `target`, then rename it over `target`.
+pub fn hard_link_replace(keep: &Path, target: &Path) -> Result<()> {
+    check_same_filesystem(keep, target)?;
+    let tmp = temp_sibling(target)?;
+    std::fs::hard_link(keep, &tmp)?;
+    if let Err(e) = std::fs::rename(&tmp, target) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Atomically replace `target` with a symbolic link to `keep`, created
+/// under a temporary name and renamed over `target`. Returns the link's
+/// target as written.
+pub fn symlink_replace(keep: &Path, target: &Path, style: SymlinkTarget) -> Result<String> {
+    let link_target = match style {
+        SymlinkTarget::Absolute => keep.to_path_buf(),
+        SymlinkTarget::Relative => {
+            let dir = target
+                .parent()
+                .with_context(|| format!("{} has no parent directory", target.display()))?;
+            pathdiff::diff_paths(keep, dir)
+                .with_context(|| format!("no relative path to {}", keep.display()))?
+        }
+    };
+    let tmp = temp_sibling(target)?;
+    make_symlink(&link_target, &tmp)?;
+    if let Err(e) = std::fs::rename(&tmp, target) {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e.into());
+    }
+    Ok(link_target.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+pub fn make_symlink(link_target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(link_target, link)
+}
+
+#[cfg(windows)]
+pub fn make_symlink(link_target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(link_target, link)
+}
+
+/// Temporary name in the same directory as `path`, so a rename over `path`
+/// never crosses a filesystem boundary.
+pub fn temp_sibling(path: &Path) -> Result<PathBuf> {
+    let dir = path
+        .parent()
+        .with_context(|| format!("{} has no parent directory", path.display()))?;
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?;
+    Ok(dir.join(format!(
+        ".{}.fdedupe-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id()
+    )))
+}
+
+#[cfg(unix)]
+fn check_same_filesystem(keep: &Path, target: &Path) -> Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let target_dir = target.parent().unwrap_or(target);
+    if std::fs::metadata(keep)?.dev() != std::fs::metadata(target_dir)?.dev() {
+        anyhow::bail!(
+            "{} and {} are on different filesystems",
+            keep.display(),
+            target.display()
+        );
+    }
+    Ok(())
+}
+
+/// Without device numbers the hard link itself fails across volumes.
+#[cfg(not(unix))]
+fn check_same_filesystem(_keep: &Path, _target: &Path) -> Result<()> {
+    Ok(())
+}
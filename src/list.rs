@@ -4,6 +4,7 @@ use std::path::Path;
 use crate::cli::ListArgs;
 use crate::config::Config;
 use crate::db::Db;
+use crate::trees;
 use crate::tui::fmt_size;
 
 pub fn run(args: &ListArgs, _config: &Config, db: &Db) -> Result<()> {
@@ -16,10 +17,31 @@ pub fn run(args: &ListArgs, _config: &Config, db: &Db) -> Result<()> {
         return crate::list_tui::run(&dir, db);
     }
 
+    print_trees(&dir, db)?;
     print_dir(&dir, args.recursive, args.follow_symlinks, db)?;
     Ok(())
 }
 
+/// Duplicate directory trees with a copy under `dir`, or containing it.
+fn print_trees(dir: &Path, db: &Db) -> Result<()> {
+    let trees: Vec<_> = trees::duplicate_trees(db)?
+        .into_iter()
+        .filter(|t| {
+            t.dirs
+                .iter()
+                .any(|d| Path::new(d).starts_with(dir) || dir.starts_with(d))
+        })
+        .collect();
+    if !trees.is_empty() {
+        println!();
+        println!("Duplicate directories:");
+        for tree in &trees {
+            println!("  {}", tree.describe());
+        }
+    }
+    Ok(())
+}
+
 fn print_dir(dir: &Path, recursive: bool, _follow_symlinks: bool, db: &Db) -> Result<()> {
     let dir_str = dir.to_string_lossy();
     let dir_row = db.get_directory(&dir_str)?;
@@ -34,7 +56,7 @@ fn print_dir(dir: &Path, recursive: bool, _follow_symlinks: bool, db: &Db) -> Re
 
     let (dup_count, dup_size) = db.duplicate_stats_under(&dir_str)?;
     println!(
-        "Duplicates: {} files, {}",
+        "Reclaimable, keeping one copy of each file here or elsewhere: {} files, {}",
         dup_count,
         fmt_size(dup_size)
     );
@@ -51,7 +73,7 @@ fn print_dir(dir: &Path, recursive: bool, _follow_symlinks: bool, db: &Db) -> Re
 
     if !child_dups.is_empty() {
         println!();
-        println!("Subdirectories with duplicates:");
+        println!("Subdirectories with reclaimable duplicates:");
         for (path, count, size) in &child_dups {
             let rel = relative_name(dir, path);
             println!("  {:40}  {} files, {}", rel + "/", count, fmt_size(*size));
@@ -155,7 +155,7 @@ fn run_loop(terminal: &mut tui::Term, root: &Path, db: &Db) -> Result<()> {
                 Span::styled(&current_str, Style::default().add_modifier(Modifier::BOLD)),
                 Span::raw("  —  "),
                 Span::styled(
-                    format!("{} duplicates, {}", dup_count, fmt_size(dup_size)),
+                    format!("{} reclaimable, {} (one copy kept)", dup_count, fmt_size(dup_size)),
                     Style::default().fg(if dup_count > 0 { Color::Yellow } else { Color::Green }),
                 ),
             ])];
@@ -173,7 +173,12 @@ fn run_loop(terminal: &mut tui::Term, root: &Path, db: &Db) -> Result<()> {
                         Style::default()
                     };
                     let label = if *dup_count > 0 {
-                        format!("  {}/   ({} dups, {})", name, dup_count, fmt_size(*dup_size))
+                        format!(
+                            "  {}/   ({} reclaimable, {})",
+                            name,
+                            dup_count,
+                            fmt_size(*dup_size)
+                        )
                     } else {
                         format!("  {}/", name)
                     };
@@ -185,7 +190,10 @@ fn run_loop(terminal: &mut tui::Term, root: &Path, db: &Db) -> Result<()> {
                     } else {
                         Style::default()
                     };
-                    let label = format!("    {}   ({})", row.name, fmt_size(row.size));
+                    let label = match &row.link_target {
+                        Some(target) => format!("    {} -> {}", row.name, target),
+                        None => format!("    {}   ({})", row.name, fmt_size(row.size)),
+                    };
                     ListItem::new(label).style(style)
                 }
             }).collect();
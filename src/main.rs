@@ -1,23 +1,39 @@
+mod action;
+mod between;
 mod cli;
 mod config;
 mod db;
+mod dedupe;
 mod hash;
+mod history;
+mod link;
 mod list;
 mod list_tui;
+mod overlap;
+mod plan;
+mod pool;
+mod preview;
+mod protect;
+mod quarantine;
 mod remove;
+mod rules;
 mod scan;
 mod scan_tui;
+mod trash;
+mod trees;
 mod tui;
+mod verify;
 
 use anyhow::Result;
 use clap::Parser;
 use std::path::PathBuf;
+use std::process::ExitCode;
 
 use cli::{Cli, Command};
 use config::Config;
 use db::Db;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let cli = Cli::parse();
     let config = Config::load()?;
 
@@ -32,8 +48,18 @@ fn main() -> Result<()> {
     match &cli.command {
         Command::Scan(args) => scan::run(args, &config, &db)?,
         Command::List(args) => list::run(args, &config, &db)?,
-        Command::Remove(args) => remove::run(args, &config, &db)?,
+        Command::Remove(args) => return remove::run(args, &config, &db),
+        Command::Link(args) => link::run(args, &config, &db)?,
+        Command::Dedupe(args) => dedupe::run(args, &config, &db)?,
+        Command::Restore(args) => quarantine::restore(args, &config, &db)?,
+        Command::Purge(args) => quarantine::purge(args, &config, &db)?,
+        Command::History(args) => history::run(args, &config, &db)?,
+        Command::Plan(args) => plan::run(args, &config, &db)?,
+        Command::Apply(args) => return plan::apply(args, &config, &db),
+        Command::Rules(args) => rules::run(args, &config, &db)?,
+        Command::Protect(args) => protect::run(args, &config, &db)?,
+        Command::Overlap(args) => overlap::run(args, &config, &db)?,
     }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
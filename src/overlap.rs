@@ -0,0 +1,260 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::cli::{OverlapArgs, OverlapFormat};
+use crate::config::Config;
+use crate::db::Db;
+use crate::tui::fmt_size;
+
+/// The pairs found by `overlaps`, and the content left out of them.
+#[derive(Debug, Default)]
+pub struct Overlaps {
+    pub pairs: Vec<Overlap>,
+    /// Hashes held by more than `max_holders` directories, and the bytes of
+    /// all their files.
+    pub skipped_hashes: usize,
+    pub skipped_bytes: i64,
+}
+
+/// How much of one directory tree has a copy in another, as of the last
+/// scan.
+#[derive(Debug, Serialize)]
+pub struct Overlap {
+    /// The directory of the pair more contained in the other.
+    pub dir: String,
+    pub other: String,
+    /// Files and bytes in `dir`'s tree.
+    pub files: usize,
+    pub bytes: i64,
+    /// Files of `dir`'s tree with a copy somewhere in `other`'s, and their
+    /// bytes.
+    pub shared_files: usize,
+    pub shared_bytes: i64,
+    /// `shared_bytes / bytes`.
+    pub ratio: f64,
+    /// The same ratio for `other` in `dir`.
+    pub other_ratio: f64,
+}
+
+pub fn run(args: &OverlapArgs, _config: &Config, db: &Db) -> Result<()> {
+    let roots: Vec<PathBuf> = args
+        .dirs
+        .iter()
+        .map(|d| {
+            d.canonicalize()
+                .with_context(|| format!("overlap {}", d.display()))
+        })
+        .collect::<Result<_>>()?;
+    let min_ratio = f64::from(args.min_ratio) / 100.0;
+    let min_shared = args.min_shared.unwrap_or(0);
+    let under_roots = |o: &Overlap| {
+        roots.is_empty()
+            || roots
+                .iter()
+                .any(|r| Path::new(&o.dir).starts_with(r) || Path::new(&o.other).starts_with(r))
+    };
+
+    let Overlaps {
+        pairs: mut found,
+        skipped_hashes,
+        skipped_bytes,
+    } = overlaps(db, args.max_holders, |o| {
+        o.ratio >= min_ratio && o.shared_bytes >= min_shared && under_roots(o)
+    })?;
+    found.sort_by(|a, b| {
+        b.ratio
+            .total_cmp(&a.ratio)
+            .then(b.shared_bytes.cmp(&a.shared_bytes))
+            .then_with(|| (&a.dir, &a.other).cmp(&(&b.dir, &b.other)))
+    });
+    if let Some(limit) = args.limit {
+        found.truncate(limit);
+    }
+
+    match args.format {
+        OverlapFormat::Json => println!("{}", serde_json::to_string_pretty(&found)?),
+        OverlapFormat::Table if found.is_empty() => {
+            println!("No directories overlap by at least {}%.", args.min_ratio)
+        }
+        OverlapFormat::Table => {
+            for o in &found {
+                println!("{:>5.1}%  {}  in  {}", o.ratio * 100.0, o.dir, o.other);
+                println!(
+                    "        {} of {} files, {} of {}; the other way round {:.1}%",
+                    o.shared_files,
+                    o.files,
+                    fmt_size(o.shared_bytes),
+                    fmt_size(o.bytes),
+                    o.other_ratio * 100.0
+                );
+            }
+        }
+    }
+    if skipped_hashes > 0 {
+        let note = format!(
+            "Not counted as shared: {} distinct files found in more than {} directories \
+             each ({} in all); raise --max-holders to include them.",
+            skipped_hashes,
+            args.max_holders,
+            fmt_size(skipped_bytes)
+        );
+        match args.format {
+            OverlapFormat::Json => eprintln!("{}", note),
+            OverlapFormat::Table => println!("\n{}", note),
+        }
+    }
+    Ok(())
+}
+
+/// Every pair of directories, neither inside the other, whose trees share
+/// duplicate content and for which `keep` holds. Files whose copies are
+/// all hard links to one inode do not count as shared, nor, unless
+/// `max_holders` is 0, files held by more than `max_holders` directories:
+/// such content (licence files, empty `__init__.py`) makes the pairs to
+/// compare grow with the square of its copies. When a pair and the pair of
+/// its parents (or of one parent and the other directory) are both kept,
+/// only the latter is returned, so a backup shows up once and not once per
+/// subdirectory.
+pub fn overlaps(db: &Db, max_holders: usize, keep: impl Fn(&Overlap) -> bool) -> Result<Overlaps> {
+    let dirs = db.all_directories()?;
+    let index: HashMap<i64, usize> = dirs.iter().enumerate().map(|(i, d)| (d.id, i)).collect();
+    let parent: Vec<Option<usize>> = dirs
+        .iter()
+        .map(|d| d.parent_id.and_then(|p| index.get(&p).copied()))
+        .collect();
+    let duplicated: HashSet<String> = db
+        .duplicate_groups()?
+        .into_iter()
+        .filter(|g| !g.is_hard_linked())
+        .map(|g| g.full_hash)
+        .collect();
+
+    // Totals of each tree, the files and bytes per duplicated hash in it,
+    // and the directories holding each such hash themselves with the bytes
+    // of all its files.
+    let all_files = db.all_files()?;
+    let mut files = vec![0usize; dirs.len()];
+    let mut bytes = vec![0i64; dirs.len()];
+    let mut content: Vec<HashMap<&str, (usize, i64)>> = vec![HashMap::new(); dirs.len()];
+    let mut holders: HashMap<&str, (Vec<usize>, i64)> = HashMap::new();
+    for file in &all_files {
+        let hash = file
+            .full_hash
+            .as_deref()
+            .filter(|h| duplicated.contains(*h));
+        let Some(&direct) = index.get(&file.directory_id) else {
+            continue;
+        };
+        if let Some(hash) = hash {
+            let entry = holders.entry(hash).or_default();
+            entry.0.push(direct);
+            entry.1 += file.size;
+        }
+        let mut dir = Some(direct);
+        while let Some(d) = dir {
+            files[d] += 1;
+            bytes[d] += file.size;
+            if let Some(hash) = hash {
+                let entry = content[d].entry(hash).or_default();
+                entry.0 += 1;
+                entry.1 += file.size;
+            }
+            dir = parent[d];
+        }
+    }
+
+    let paths: Vec<&Path> = dirs.iter().map(|d| Path::new(&d.canonical_path)).collect();
+    let nested =
+        |a: usize, b: usize| paths[a].starts_with(paths[b]) || paths[b].starts_with(paths[a]);
+    let ancestors = |d: usize| std::iter::successors(Some(d), |&d| parent[d]);
+    // (a, b) -> files and bytes of a's tree with a copy in b's.
+    let mut shared: HashMap<(usize, usize), (usize, i64)> = HashMap::new();
+    let (mut skipped_hashes, mut skipped_bytes) = (0, 0);
+    for (hash, (mut direct, hash_bytes)) in holders {
+        direct.sort_unstable();
+        direct.dedup();
+        if max_holders > 0 && direct.len() > max_holders {
+            skipped_hashes += 1;
+            skipped_bytes += hash_bytes;
+            continue;
+        }
+        // Two trees holding `hash` that are not nested lie on the paths from
+        // two holders up to, but not including, the directory both are in.
+        let chains: Vec<Vec<usize>> = direct.iter().map(|&d| ancestors(d).collect()).collect();
+        let mut counted: HashSet<(usize, usize)> = HashSet::new();
+        for (i, x) in chains.iter().enumerate() {
+            for (j, y) in chains.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                for &a in &below(x, y) {
+                    for &b in &below(y, x) {
+                        if nested(a, b) || !counted.insert((a, b)) {
+                            continue;
+                        }
+                        let (n, size) = content[a][hash];
+                        let entry = shared.entry((a, b)).or_default();
+                        entry.0 += n;
+                        entry.1 += size;
+                    }
+                }
+            }
+        }
+    }
+
+    let ratio = |a: usize, b: usize| shared[&(a, b)].1 as f64 / bytes[a] as f64;
+    let mut kept: HashMap<(usize, usize), Overlap> = HashMap::new();
+    for (&(a, b), &(shared_files, shared_bytes)) in &shared {
+        // Each pair once, from the side more contained in the other.
+        let (ra, rb) = (ratio(a, b), ratio(b, a));
+        if ra < rb || (ra == rb && paths[a] > paths[b]) {
+            continue;
+        }
+        let overlap = Overlap {
+            dir: dirs[a].canonical_path.clone(),
+            other: dirs[b].canonical_path.clone(),
+            files: files[a],
+            bytes: bytes[a],
+            shared_files,
+            shared_bytes,
+            ratio: ra,
+            other_ratio: rb,
+        };
+        if keep(&overlap) {
+            kept.insert((a.min(b), a.max(b)), overlap);
+        }
+    }
+
+    let pair = |a: usize, b: usize| (a.min(b), a.max(b));
+    let covered = |&(a, b): &(usize, usize)| {
+        [
+            parent[a].zip(parent[b]),
+            parent[a].map(|pa| (pa, b)),
+            parent[b].map(|pb| (a, pb)),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|(x, y)| x != y && kept.contains_key(&pair(x, y)))
+    };
+    let topmost: Vec<(usize, usize)> = kept.keys().copied().filter(|p| !covered(p)).collect();
+    Ok(Overlaps {
+        pairs: topmost
+            .into_iter()
+            .filter_map(|p| kept.remove(&p))
+            .collect(),
+        skipped_hashes,
+        skipped_bytes,
+    })
+}
+
+/// The start of `chain`, a directory and its ancestors, up to the first
+/// directory also in `other`.
+fn below(chain: &[usize], other: &[usize]) -> Vec<usize> {
+    chain
+        .iter()
+        .copied()
+        .take_while(|d| !other.contains(d))
+        .collect()
+}
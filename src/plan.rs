@@ -0,0 +1,306 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::ExitCode;
+
+use crate::action;
+use crate::cli::{ApplyArgs, PlanArgs, PlanFormat};
+use crate::config::Config;
+use crate::db::{Db, DuplicateGroup, FileRow};
+use crate::hash;
+use crate::protect::Protected;
+use crate::remove::BatchOutcome;
+use crate::rules;
+use crate::tui::fmt_size;
+use crate::verify::KeptFileChanged;
+
+/// Keep/delete decisions for every duplicate group, written by `plan` and
+/// carried out by `apply`.
+#[derive(Serialize, Deserialize)]
+pub struct Plan {
+    /// Unix timestamp of the `plan` run.
+    pub created_at: i64,
+    pub groups: Vec<PlanGroup>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlanGroup {
+    pub full_hash: String,
+    /// Pattern of the rule that made the decisions; null when they are left
+    /// to the reader. Clear it when changing the decisions by hand.
+    #[serde(default)]
+    pub rule: Option<String>,
+    pub files: Vec<PlanFile>,
+}
+
+/// A file as the scan recorded it when the plan was written.
+#[derive(Serialize, Deserialize)]
+pub struct PlanFile {
+    pub path: String,
+    pub size: i64,
+    pub modified_at: i64,
+    pub decision: Decision,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Keep,
+    Delete,
+    Undecided,
+}
+
+// ── plan ─────────────────────────────────────────────────────────────────────
+
+pub fn run(args: &PlanArgs, config: &Config, db: &Db) -> Result<()> {
+    let rules = rules::load(config, db)?;
+    let protected = Protected::load(config, db)?;
+    let (covered, groups): (Vec<_>, Vec<_>) = db
+        .duplicate_groups()?
+        .into_iter()
+        .filter(|g| !g.is_hard_linked())
+        .partition(|g| protected.covers(g));
+    let groups: Vec<PlanGroup> = groups
+        .iter()
+        .map(|group| {
+            let keepers = rules::keepers(&group.files, &rules);
+            PlanGroup {
+                full_hash: group.full_hash.clone(),
+                rule: keepers.as_ref().map(|(_, rule)| rule.describe()),
+                files: group
+                    .files
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| PlanFile {
+                        path: f.canonical_path.clone(),
+                        size: f.size,
+                        modified_at: f.modified_at,
+                        decision: match &keepers {
+                            Some((kept, _)) if kept.contains(&i) => Decision::Keep,
+                            Some(_) if protected.contains(&f.canonical_path) => Decision::Keep,
+                            Some(_) => Decision::Delete,
+                            None => Decision::Undecided,
+                        },
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    let decided = groups.iter().filter(|g| g.rule.is_some()).count();
+    let plan = Plan {
+        created_at: chrono::Utc::now().timestamp(),
+        groups,
+    };
+
+    let format = args
+        .format
+        .unwrap_or_else(|| format_of(args.output.as_deref().unwrap_or(Path::new(""))));
+    let text = match format {
+        PlanFormat::Json => serde_json::to_string_pretty(&plan)? + "\n",
+        PlanFormat::Yaml => serde_yaml::to_string(&plan)?,
+    };
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!(
+                "Wrote {} groups to {} ({} decided by rules, {} undecided)",
+                plan.groups.len(),
+                path.display(),
+                decided,
+                plan.groups.len() - decided
+            );
+        }
+        None => print!("{}", text),
+    }
+    if !covered.is_empty() {
+        eprintln!(
+            "Left out {} group(s) where every copy is protected.",
+            covered.len()
+        );
+    }
+    Ok(())
+}
+
+/// YAML for `*.yaml` / `*.yml`, JSON otherwise.
+fn format_of(path: &Path) -> PlanFormat {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("yaml" | "yml") => PlanFormat::Yaml,
+        _ => PlanFormat::Json,
+    }
+}
+
+// ── apply ────────────────────────────────────────────────────────────────────
+
+pub fn apply(args: &ApplyArgs, config: &Config, db: &Db) -> Result<ExitCode> {
+    let text = std::fs::read_to_string(&args.plan)
+        .with_context(|| format!("reading {}", args.plan.display()))?;
+    let plan: Plan = match format_of(&args.plan) {
+        PlanFormat::Json => serde_json::from_str(&text)?,
+        PlanFormat::Yaml => serde_yaml::from_str(&text)?,
+    };
+    if plan.groups.is_empty() {
+        println!("The plan has no duplicate groups.");
+        return Ok(BatchOutcome::NothingToDo.exit_code());
+    }
+
+    let opts = action::options(&args.dispose, config, db)?;
+    let dry = opts.dry_run;
+    let mut acted = 0;
+    let mut undecided = 0;
+    let mut files = 0;
+    let mut freed = 0;
+    let mut failures = Vec::new();
+
+    for group in &plan.groups {
+        if group.files.iter().all(|f| opts.protected.contains(&f.path)) {
+            println!(
+                "Skipped {}: every copy is protected",
+                group
+                    .files
+                    .first()
+                    .map_or("empty group", |f| f.path.as_str())
+            );
+            continue;
+        }
+        let decisions: Vec<Decision> = group.files.iter().map(|f| f.decision).collect();
+        if decisions.contains(&Decision::Undecided) || !decisions.contains(&Decision::Keep) {
+            undecided += 1;
+            continue;
+        }
+
+        // Every file must still be what the plan describes, down to its hash.
+        let rows: Vec<Result<FileRow>> =
+            group.files.iter().map(|f| current(f, group, db)).collect();
+        // Prefer a kept copy that is not kept just for being protected.
+        let kept: Vec<usize> = (0..decisions.len())
+            .filter(|&i| decisions[i] == Decision::Keep)
+            .collect();
+        let keep_idx = kept
+            .iter()
+            .copied()
+            .find(|&i| !opts.protected.contains(&group.files[i].path))
+            .unwrap_or(kept[0]);
+        let keep = match &rows[keep_idx] {
+            Ok(row) => row,
+            Err(e) => {
+                failures.push(format!("Skipped group: kept copy {:#}", e));
+                continue;
+            }
+        };
+        acted += 1;
+        println!("Keep: {}", keep.canonical_path);
+
+        let mut done = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            if decisions[i] != Decision::Delete {
+                continue;
+            }
+            let result = row
+                .as_ref()
+                .map_err(|e| anyhow::anyhow!("{:#}", e))
+                .and_then(|target| action::apply(&opts, keep, target, group.rule.as_deref(), db));
+            match result {
+                Ok(()) => {
+                    println!(
+                        "  {} {}",
+                        if dry { "would remove" } else { "removed" },
+                        group.files[i].path
+                    );
+                    done.push(i);
+                }
+                Err(e) => {
+                    failures.push(format!("Failed: {:#}", e));
+                    if e.is::<KeptFileChanged>() {
+                        break;
+                    }
+                }
+            }
+        }
+        files += done.len();
+        freed += bytes_freed(group, &rows, &done);
+    }
+
+    for msg in &failures {
+        eprintln!("{}", msg);
+    }
+    if acted > 0 {
+        println!();
+    }
+    println!(
+        "{}{} of {} groups applied: {} {} files, freeing {}",
+        if dry { "[DRY RUN] " } else { "" },
+        acted,
+        plan.groups.len(),
+        if dry { "would remove" } else { "removed" },
+        files,
+        fmt_size(freed)
+    );
+    if undecided > 0 {
+        println!("{} groups left undecided in the plan", undecided);
+    }
+    if !failures.is_empty() {
+        println!("{} refused or failed (see above)", failures.len());
+    }
+
+    Ok(if !failures.is_empty() {
+        BatchOutcome::Errors
+    } else if undecided > 0 {
+        BatchOutcome::Unresolved
+    } else {
+        BatchOutcome::Done
+    }
+    .exit_code())
+}
+
+/// The DB row of `file`, provided the file on disk still has the size,
+/// mtime and hash the plan recorded.
+fn current(file: &PlanFile, group: &PlanGroup, db: &Db) -> Result<FileRow> {
+    let Some(row) = db.file_by_path(&file.path)? else {
+        bail!("{} is no longer in the database", file.path);
+    };
+    if row.size != file.size
+        || row.modified_at != file.modified_at
+        || row.full_hash.as_deref() != Some(group.full_hash.as_str())
+    {
+        bail!(
+            "{} was rescanned with different content since the plan was written",
+            file.path
+        );
+    }
+    let meta = std::fs::metadata(&file.path).with_context(|| format!("reading {}", file.path))?;
+    let meta = crate::scan::file_meta(&meta);
+    if meta.size != file.size || meta.modified_at != file.modified_at {
+        bail!("{} changed since the plan was written", file.path);
+    }
+    let hash =
+        hash::full_hash(Path::new(&file.path)).with_context(|| format!("hashing {}", file.path))?;
+    if hash != group.full_hash {
+        bail!(
+            "{} no longer has the content recorded in the plan",
+            file.path
+        );
+    }
+    Ok(row)
+}
+
+/// Bytes freed by the files at `done`, counted on the group as the DB has
+/// it so hard links are only counted once.
+fn bytes_freed(group: &PlanGroup, rows: &[Result<FileRow>], done: &[usize]) -> i64 {
+    let files: Vec<FileRow> = rows
+        .iter()
+        .filter_map(|r| r.as_ref().ok().cloned())
+        .collect();
+    let done: Vec<usize> = done
+        .iter()
+        .filter_map(|&i| {
+            files
+                .iter()
+                .position(|f| f.canonical_path == group.files[i].path)
+        })
+        .collect();
+    DuplicateGroup {
+        full_hash: group.full_hash.clone(),
+        files,
+    }
+    .bytes_freed(&done)
+}
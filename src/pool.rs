@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Fixed-size pool of worker threads pulling jobs from a shared queue.
+///
+/// Every job is turned into a result by `work` and sent to the `results`
+/// channel, so several pools can feed one consumer (the single DB writer).
+pub struct Pool<J: Send + 'static> {
+    jobs: Option<Sender<J>>,
+    workers: usize,
+    handles: Vec<JoinHandle<()>>,
+    queued: Arc<AtomicUsize>,
+    busy: Arc<AtomicUsize>,
+    stopped: Arc<AtomicBool>,
+}
+
+/// Snapshot of how loaded a pool is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Load {
+    pub workers: usize,
+    pub busy: usize,
+    pub queued: usize,
+}
+
+impl<J: Send + 'static> Pool<J> {
+    pub fn new<R, F>(workers: usize, results: Sender<R>, work: F) -> Self
+    where
+        R: Send + 'static,
+        F: Fn(J) -> R + Send + Sync + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<J>();
+        let rx = Arc::new(Mutex::new(rx));
+        let work = Arc::new(work);
+        let queued = Arc::new(AtomicUsize::new(0));
+        let busy = Arc::new(AtomicUsize::new(0));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let workers = workers.max(1);
+        let mut handles = Vec::with_capacity(workers);
+        for _ in 0..workers {
+            let rx = Arc::clone(&rx);
+            let work = Arc::clone(&work);
+            let results = results.clone();
+            let queued = Arc::clone(&queued);
+            let busy = Arc::clone(&busy);
+            let stopped = Arc::clone(&stopped);
+            handles.push(std::thread::spawn(move || {
+                worker(&rx, &*work, &results, &queued, &busy, &stopped)
+            }));
+        }
+
+        Self {
+            jobs: Some(tx),
+            workers,
+            handles,
+            queued,
+            busy,
+            stopped,
+        }
+    }
+
+    pub fn submit(&self, job: J) {
+        if let Some(tx) = &self.jobs {
+            self.queued.fetch_add(1, Ordering::SeqCst);
+            if tx.send(job).is_err() {
+                self.queued.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    pub fn load(&self) -> Load {
+        Load {
+            workers: self.workers,
+            busy: self.busy.load(Ordering::SeqCst),
+            queued: self.queued.load(Ordering::SeqCst),
+        }
+    }
+}
+
+impl<J: Send + 'static> Drop for Pool<J> {
+    fn drop(&mut self) {
+        // Queued jobs are discarded and closing the channel lets the workers
+        // exit once their current job is done. They are joined so that no
+        // file is still being read after an interrupted scan returns.
+        self.stopped.store(true, Ordering::SeqCst);
+        self.jobs.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn worker<J, R>(
+    rx: &Mutex<Receiver<J>>,
+    work: &(dyn Fn(J) -> R + Send + Sync),
+    results: &Sender<R>,
+    queued: &AtomicUsize,
+    busy: &AtomicUsize,
+    stopped: &AtomicBool,
+) {
+    loop {
+        // Hold the lock only while dequeuing so other workers can pick up jobs.
+        let job = match rx.lock() {
+            Ok(rx) => match rx.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+        queued.fetch_sub(1, Ordering::SeqCst);
+        if stopped.load(Ordering::SeqCst) {
+            continue;
+        }
+        busy.fetch_add(1, Ordering::SeqCst);
+        let result = work(job);
+        busy.fetch_sub(1, Ordering::SeqCst);
+        if results.send(result).is_err() {
+            return;
+        }
+    }
+}
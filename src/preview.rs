@@ -0,0 +1,208 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::fs::File;
+use std::io::Read;
+
+use crate::db::FileRow;
+use crate::history::local_time;
+use crate::tui::fmt_size;
+use crate::verify;
+
+/// Bytes read from the start of the file.
+const HEAD: usize = 4096;
+/// Lines of text shown.
+const TEXT_LINES: usize = 40;
+/// Bytes shown in the hex dump of a binary file.
+const HEX_BYTES: usize = 256;
+
+/// Metadata and the first bytes of `file`, read from disk now, with a note
+/// on whether it still matches the last scan.
+pub fn preview(file: &FileRow) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let label = |s: &'static str| Span::styled(s, Style::default().fg(Color::DarkGray));
+
+    match verify::unchanged(file) {
+        Ok(()) => lines.push(Line::from(Span::styled(
+            "Unchanged since the scan",
+            Style::default().fg(Color::Green),
+        ))),
+        Err(e) => lines.push(Line::from(Span::styled(
+            format!("{:#}", e),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ))),
+    }
+
+    let meta = match std::fs::symlink_metadata(&file.canonical_path) {
+        Ok(m) => m,
+        Err(e) => {
+            lines.push(Line::from(format!("Cannot read: {}", e)));
+            return lines;
+        }
+    };
+    lines.push(Line::from(vec![
+        label("size      "),
+        Span::raw(format!(
+            "{} ({} bytes)",
+            fmt_size(meta.len() as i64),
+            meta.len()
+        )),
+    ]));
+    let mtime = crate::scan::file_meta(&meta).modified_at;
+    lines.push(Line::from(vec![
+        label("modified  "),
+        Span::raw(local_time(mtime)),
+    ]));
+    lines.extend(unix_lines(&meta));
+    lines.push(Line::from(""));
+
+    let mut head = Vec::with_capacity(HEAD);
+    let read =
+        File::open(&file.canonical_path).and_then(|f| f.take(HEAD as u64).read_to_end(&mut head));
+    if let Err(e) = read {
+        lines.push(Line::from(format!("Cannot read: {}", e)));
+        return lines;
+    }
+    match text(&head) {
+        Some(text) => lines.extend(
+            text.lines()
+                .take(TEXT_LINES)
+                .map(|l| Line::from(l.replace('\t', "    "))),
+        ),
+        None => lines.extend(hex_dump(&head[..head.len().min(HEX_BYTES)])),
+    }
+    lines
+}
+
+/// `head` as text: valid UTF-8 (a character cut off at the end aside)
+/// without control characters other than tab and line breaks, which would
+/// be sent to the terminal as they are.
+fn text(head: &[u8]) -> Option<&str> {
+    let text = match std::str::from_utf8(head) {
+        Ok(s) => s,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    let printable = !text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'));
+    printable.then_some(text)
+}
+
+/// `xxd`-style lines: offset, 16 bytes in hex, printable ASCII.
+fn hex_dump(bytes: &[u8]) -> Vec<Line<'static>> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            Line::from(vec![
+                Span::styled(
+                    format!("{:08x}  ", i * 16),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::raw(format!("{:<48} {}", hex.join(" "), ascii)),
+            ])
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn unix_lines(meta: &std::fs::Metadata) -> Vec<Line<'static>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let label = |s: &'static str| Span::styled(s, Style::default().fg(Color::DarkGray));
+    let owner = format!(
+        "{}:{}",
+        user_name(meta.uid()).unwrap_or_else(|| meta.uid().to_string()),
+        group_name(meta.gid()).unwrap_or_else(|| meta.gid().to_string())
+    );
+    vec![
+        Line::from(vec![
+            label("mode      "),
+            Span::raw(format!(
+                "{:04o} {}",
+                meta.mode() & 0o7777,
+                permissions(meta.mode())
+            )),
+        ]),
+        Line::from(vec![label("owner     "), Span::raw(owner)]),
+        Line::from(vec![
+            label("inode     "),
+            Span::raw(format!(
+                "{} on device {}, {} link(s)",
+                meta.ino(),
+                meta.dev(),
+                meta.nlink()
+            )),
+        ]),
+    ]
+}
+
+#[cfg(not(unix))]
+fn unix_lines(meta: &std::fs::Metadata) -> Vec<Line<'static>> {
+    let readonly = if meta.permissions().readonly() {
+        "read-only"
+    } else {
+        "writable"
+    };
+    vec![Line::from(format!("mode      {}", readonly))]
+}
+
+/// `rwxr-x---` for the low nine bits of `mode`.
+#[cfg(unix)]
+fn permissions(mode: u32) -> String {
+    (0..9)
+        .map(|i| {
+            if mode & (0o400 >> i) == 0 {
+                '-'
+            } else {
+                ['r', 'w', 'x'][i % 3]
+            }
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    // SAFETY: getpwuid returns NULL or a pointer to a static entry that stays
+    // valid until the next call; the name is copied out right away.
+    unsafe {
+        let pw = libc::getpwuid(uid);
+        if pw.is_null() {
+            return None;
+        }
+        Some(
+            std::ffi::CStr::from_ptr((*pw).pw_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    // SAFETY: as for getpwuid in `user_name`.
+    unsafe {
+        let gr = libc::getgrgid(gid);
+        if gr.is_null() {
+            return None;
+        }
+        Some(
+            std::ffi::CStr::from_ptr((*gr).gr_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::cli::{ProtectArgs, ProtectCommand};
+use crate::config::Config;
+use crate::db::{Db, DuplicateGroup};
+
+/// Globs of paths that no action may remove or replace: the config's
+/// `protected` list plus the `protected` table.
+#[derive(Clone)]
+pub struct Protected {
+    set: GlobSet,
+}
+
+impl Protected {
+    pub fn load(config: &Config, db: &Db) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in config.protected.iter().chain(&db.protected_patterns()?) {
+            builder.add(
+                Glob::new(pattern)
+                    .with_context(|| format!("invalid protected glob '{}'", pattern))?,
+            );
+        }
+        Ok(Self {
+            set: builder.build()?,
+        })
+    }
+
+    /// Whether the canonical path `path` is protected.
+    pub fn contains(&self, path: &str) -> bool {
+        self.set.is_match(path)
+    }
+
+    /// Whether every copy in `group` is protected, leaving nothing to act on.
+    pub fn covers(&self, group: &DuplicateGroup) -> bool {
+        group.files.iter().all(|f| self.contains(&f.canonical_path))
+    }
+}
+
+pub fn run(args: &ProtectArgs, config: &Config, db: &Db) -> Result<()> {
+    match &args.command {
+        ProtectCommand::List => {
+            let stored = db.protected_patterns()?;
+            if config.protected.is_empty() && stored.is_empty() {
+                println!("No protected paths.");
+            }
+            for p in &config.protected {
+                println!("{}  (config)", p);
+            }
+            for p in &stored {
+                println!("{}", p);
+            }
+        }
+        ProtectCommand::Add { patterns } => {
+            for p in patterns {
+                Glob::new(p).with_context(|| format!("invalid glob '{}'", p))?;
+            }
+            for p in patterns {
+                if db.insert_protected(p)? {
+                    println!("Protected {}", p);
+                } else {
+                    println!("{} is already protected", p);
+                }
+            }
+        }
+        ProtectCommand::Rm { patterns } => {
+            let mut missing = Vec::new();
+            for p in patterns {
+                if db.delete_protected(p)? {
+                    println!("No longer protected: {}", p);
+                } else if config.protected.contains(p) {
+                    missing.push(format!("{} (set in the config file)", p));
+                } else {
+                    missing.push(p.clone());
+                }
+            }
+            if !missing.is_empty() {
+                bail!("not in the protected table: {}", missing.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
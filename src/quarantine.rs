@@ -0,0 +1,335 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::cli::{PurgeArgs, RestoreArgs};
+use crate::config::Config;
+use crate::db::{Db, FileRow, QuarantineRow};
+use crate::link;
+use crate::scan;
+use crate::trash;
+use crate::tui::fmt_size;
+
+/// Quarantine directory of one `remove --quarantine` session.
+#[derive(Clone)]
+pub struct Quarantine {
+    root: PathBuf,
+    session_id: i64,
+}
+
+impl Quarantine {
+    /// Create the quarantine directory if needed.
+    pub fn open(dir: &Path, session_id: i64) -> Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        Ok(Self {
+            root: dir.canonicalize()?,
+            session_id,
+        })
+    }
+
+    /// Move `file` under the quarantine root at its mirrored original path and
+    /// record it. Returns the quarantine path.
+    ///
+    /// The row is written first and dropped again if the move fails, so a
+    /// file in quarantine always has a row for `restore` and `purge` to find.
+    pub fn store(&self, file: &FileRow, db: &Db) -> Result<PathBuf> {
+        let original = Path::new(&file.canonical_path);
+        if original.starts_with(&self.root) {
+            bail!("{} is inside the quarantine directory", original.display());
+        }
+        let dest = free_path(&self.root.join(mirrored(original)));
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        let id = db.insert_quarantine(&QuarantineRow {
+            id: 0,
+            session_id: self.session_id,
+            original_path: file.canonical_path.clone(),
+            quarantine_root: self.root.to_string_lossy().into_owned(),
+            quarantine_path: dest.to_string_lossy().into_owned(),
+            size: file.size,
+            modified_at: file.modified_at,
+            fast_hash: file.fast_hash.clone(),
+            full_hash: file.full_hash.clone(),
+            quarantined_at: chrono::Utc::now().timestamp(),
+        })?;
+        if let Err(e) = move_file(original, &dest) {
+            db.delete_quarantine(id)?;
+            return Err(e);
+        }
+        Ok(dest)
+    }
+}
+
+// ── restore ──────────────────────────────────────────────────────────────────
+
+pub fn restore(args: &RestoreArgs, _config: &Config, db: &Db) -> Result<()> {
+    // Keyed by id: the selectors may overlap.
+    let mut rows: BTreeMap<i64, QuarantineRow> = BTreeMap::new();
+    for path in &args.paths {
+        let path = path.canonicalize().or_else(|_| std::path::absolute(path))?;
+        rows.extend(
+            db.quarantined_under(&path.to_string_lossy())?
+                .into_iter()
+                .map(|r| (r.id, r)),
+        );
+    }
+    if let Some(hash) = &args.group {
+        rows.extend(
+            db.quarantined_with_hash(hash)?
+                .into_iter()
+                .map(|r| (r.id, r)),
+        );
+    }
+    if let Some(session) = args.session {
+        rows.extend(
+            db.quarantined_in_session(session)?
+                .into_iter()
+                .map(|r| (r.id, r)),
+        );
+    }
+    if rows.is_empty() {
+        println!("Nothing in quarantine matches.");
+        return Ok(());
+    }
+
+    let verb = if args.dry_run {
+        "Would restore"
+    } else {
+        "Restored"
+    };
+    let mut restored = 0;
+    let mut bytes = 0;
+    let mut skipped = 0;
+    let mut failed = 0;
+    for row in rows.values() {
+        let original = Path::new(&row.original_path);
+        if args.dry_run {
+            println!("  would restore {}", original.display());
+            restored += 1;
+            bytes += row.size;
+            continue;
+        }
+        if original.symlink_metadata().is_ok() {
+            eprintln!(
+                "  skipped {}: a file already exists there",
+                original.display()
+            );
+            skipped += 1;
+            continue;
+        }
+        let quarantined = Path::new(&row.quarantine_path);
+        let moved = original
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| move_file(quarantined, original));
+        if let Err(e) = moved {
+            eprintln!("  failed: {}: {:#}", original.display(), e);
+            failed += 1;
+            continue;
+        }
+        db.delete_quarantine(row.id)?;
+        prune_empty_dirs(quarantined, Path::new(&row.quarantine_root));
+        reinstate(row, db)?;
+        println!("  restored {}", original.display());
+        restored += 1;
+        bytes += row.size;
+    }
+
+    println!();
+    println!(
+        "{} {} files ({}){}",
+        verb,
+        restored,
+        fmt_size(bytes),
+        summary_suffix(skipped, failed)
+    );
+    Ok(())
+}
+
+/// Put the restored file back into `files` if its directory is known and
+/// its stat data is what was recorded, so it is a duplicate again without a
+/// rescan. Otherwise the next scan picks it up.
+fn reinstate(row: &QuarantineRow, db: &Db) -> Result<()> {
+    let path = Path::new(&row.original_path);
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(());
+    };
+    let Some(dir_row) = db.get_directory(&dir.to_string_lossy())? else {
+        return Ok(());
+    };
+    let meta = scan::file_meta(&std::fs::metadata(path)?);
+    if meta.size != row.size || meta.modified_at != row.modified_at {
+        return Ok(());
+    }
+    db.upsert_file(
+        dir_row.id,
+        &name.to_string_lossy(),
+        &row.original_path,
+        &meta,
+        row.fast_hash.as_deref(),
+        row.full_hash.as_deref(),
+    )?;
+    Ok(())
+}
+
+// ── purge ────────────────────────────────────────────────────────────────────
+
+pub fn purge(args: &PurgeArgs, _config: &Config, db: &Db) -> Result<()> {
+    let cutoff = chrono::Utc::now().timestamp() - args.older_than;
+    let rows = db.quarantined_before(cutoff)?;
+    if rows.is_empty() {
+        println!("Nothing in quarantine is that old.");
+        return Ok(());
+    }
+
+    let verb = if args.dry_run {
+        "Would purge"
+    } else {
+        "Purged"
+    };
+    let mut purged = 0;
+    let mut bytes = 0;
+    let mut failed = 0;
+    for row in &rows {
+        let quarantined = Path::new(&row.quarantine_path);
+        if args.dry_run {
+            println!("  would purge {}", quarantined.display());
+            purged += 1;
+            bytes += row.size;
+            continue;
+        }
+        match std::fs::remove_file(quarantined) {
+            Ok(()) => {}
+            // Already gone: only the record is left to drop.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                eprintln!("  failed: {}: {}", quarantined.display(), e);
+                failed += 1;
+                continue;
+            }
+        }
+        db.delete_quarantine(row.id)?;
+        prune_empty_dirs(quarantined, Path::new(&row.quarantine_root));
+        purged += 1;
+        bytes += row.size;
+    }
+
+    println!(
+        "{} {} files, freeing {}{}",
+        verb,
+        purged,
+        fmt_size(bytes),
+        summary_suffix(0, failed)
+    );
+    Ok(())
+}
+
+/// Parse an age such as `30d`, `12h` or `2w` into seconds.
+pub fn parse_age(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let n: i64 = number
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", s))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        _ => return Err(format!("unknown unit in '{}'; use s, m, h, d or w", s)),
+    };
+    Ok(n * secs)
+}
+
+// ── Helpers ──────────────────────────────────────────────────────────────────
+
+/// `/a/b/c.txt` → `a/b/c.txt`; a Windows drive prefix becomes a plain
+/// directory (`C:` → `C`).
+fn mirrored(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(p) => Some(PathBuf::from(p)),
+            Component::Prefix(p) => Some(PathBuf::from(
+                p.as_os_str()
+                    .to_string_lossy()
+                    .chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .collect::<String>(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `path`, or `name.N.ext` next to it when `path` is taken (the same
+/// original path quarantined more than once).
+fn free_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut candidate = path.to_path_buf();
+    let mut n = 1;
+    while candidate.symlink_metadata().is_ok() {
+        n += 1;
+        candidate = path.with_file_name(trash::numbered(&name, n));
+    }
+    candidate
+}
+
+/// Rename `from` to `to`, or copy and delete when they are on different
+/// filesystems. The copy keeps the modification time; a symbolic link is
+/// recreated rather than followed.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("moving {} to {}", from.display(), to.display()))
+        }
+    }
+    if from.is_symlink() {
+        let target = std::fs::read_link(from)?;
+        link::make_symlink(&target, to)
+            .with_context(|| format!("linking {} to {}", to.display(), target.display()))?;
+        return std::fs::remove_file(from).with_context(|| format!("removing {}", from.display()));
+    }
+    let copied = std::fs::copy(from, to).and_then(|_| {
+        let modified = std::fs::metadata(from)?.modified()?;
+        std::fs::File::options()
+            .write(true)
+            .open(to)?
+            .set_modified(modified)
+    });
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(to);
+        return Err(e).with_context(|| format!("copying {} to {}", from.display(), to.display()));
+    }
+    std::fs::remove_file(from).with_context(|| format!("removing {}", from.display()))
+}
+
+/// Remove directories left empty below `root`, starting at `file`'s parent.
+fn prune_empty_dirs(file: &Path, root: &Path) {
+    let mut dir = file.parent();
+    while let Some(d) = dir {
+        if d == root || !d.starts_with(root) || std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+fn summary_suffix(skipped: usize, failed: usize) -> String {
+    let mut s = String::new();
+    if skipped > 0 {
+        s.push_str(&format!("; {} skipped", skipped));
+    }
+    if failed > 0 {
+        s.push_str(&format!("; {} failed", failed));
+    }
+    s
+}
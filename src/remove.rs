@@ -1,31 +1,250 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use crossterm::event::KeyCode;
-use globset::{Glob, GlobSetBuilder};
 use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
 };
-use std::time::Duration;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-use crate::cli::RemoveArgs;
+use crate::action::{self, ActionOptions};
+use crate::between;
+use crate::cli::{GroupFilter, GroupSort, RemoveAction, RemoveArgs, RuleKind, ScopeMatch};
 use crate::config::Config;
-use crate::db::{Db, DuplicateGroup, FileRow};
+use crate::db::{Db, DuplicateGroup, RuleRow};
+use crate::link;
+use crate::preview;
+use crate::protect::Protected;
+use crate::rules;
+use crate::trees;
 use crate::tui::{self, fmt_size};
+use crate::verify::KeptFileChanged;
 
-pub fn run(args: &RemoveArgs, _config: &Config, db: &Db) -> Result<()> {
-    let mut groups = db.duplicate_groups()?;
+pub fn run(args: &RemoveArgs, config: &Config, db: &Db) -> Result<ExitCode> {
+    // Groups whose paths are all hard links to one inode have nothing to reclaim
+    let (linked, groups): (Vec<_>, Vec<_>) = db
+        .duplicate_groups()?
+        .into_iter()
+        .partition(DuplicateGroup::is_hard_linked);
     if groups.is_empty() {
         println!("No duplicates found. Run 'fdedupe scan' first.");
-        return Ok(());
+        report_linked(&linked);
+        return Ok(if args.batch {
+            BatchOutcome::NothingToDo.exit_code()
+        } else {
+            ExitCode::SUCCESS
+        });
     }
 
-    let rules = db.all_rules()?;
+    let opts = RemoveOptions {
+        action: action::options(&args.dispose, config, db)?,
+        sort: args.sort,
+    };
+    if args.trees {
+        trees::run(&opts.action, db)?;
+        report_linked(&linked);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let (covered, groups): (Vec<_>, Vec<_>) = groups
+        .into_iter()
+        .partition(|g| opts.action.protected.covers(g));
+    if groups.is_empty() {
+        report_linked(&linked);
+        link::report_protected(&covered);
+        return Ok(if args.batch {
+            BatchOutcome::NothingToDo.exit_code()
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+
+    let (mut groups, hidden): (Vec<_>, Vec<_>) = {
+        let keep = filter(&args.filter)?;
+        groups.into_iter().partition(|g| keep(g))
+    };
+    if groups.is_empty() {
+        println!(
+            "None of the {} duplicate groups match the filters.",
+            hidden.len()
+        );
+        return Ok(if args.batch {
+            BatchOutcome::NothingToDo.exit_code()
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+    groups.sort_by(|a, b| compare(a, b, opts.sort));
+
+    if let Some(dirs) = &args.between {
+        between::run(&groups, &dirs[0], &dirs[1], &opts.action, db)?;
+        report_linked(&linked);
+        link::report_protected(&covered);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let rules = rules::load(config, db)?;
+    if args.batch {
+        let outcome = run_batch(&groups, &rules, &opts, db)?;
+        report_linked(&linked);
+        link::report_protected(&covered);
+        return Ok(outcome.exit_code());
+    }
+
+    let mut failures = Vec::new();
     let mut terminal = tui::enter()?;
-    let result = run_loop(&mut terminal, &mut groups, &rules, args.dry_run, db);
+    let result = run_loop(&mut terminal, &groups, &rules, &opts, db, &mut failures);
     tui::leave(&mut terminal)?;
-    result
+    for msg in &failures {
+        eprintln!("{}", msg);
+    }
+    match result? {
+        LoopEnd::Committed {
+            groups,
+            files,
+            freed,
+        } => println!(
+            "{}{} {} files in {} groups, freeing {}",
+            if opts.action.dry_run { "[DRY RUN] " } else { "" },
+            if opts.action.dry_run { "Would remove" } else { "Removed" },
+            files,
+            groups,
+            fmt_size(freed)
+        ),
+        LoopEnd::Quit { staged } if staged > 0 => println!(
+            "Discarded {} staged groups; nothing was removed.",
+            staged
+        ),
+        LoopEnd::Quit { .. } => {}
+    }
+    report_linked(&linked);
+    link::report_protected(&covered);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Settings that apply to every group of one `remove` session.
+struct RemoveOptions {
+    action: ActionOptions,
+    /// Initial order of the groups; the TUI can change it.
+    sort: GroupSort,
+}
+
+fn report_linked(linked: &[DuplicateGroup]) {
+    if !linked.is_empty() {
+        println!(
+            "Skipped {} group(s) that are already hard-linked (no space to reclaim).",
+            linked.len()
+        );
+    }
+}
+
+// ── Group order and filters ───────────────────────────────────────────────────
+
+/// Predicate for the groups `filter` lets through; every given condition
+/// must hold.
+fn filter(filter: &GroupFilter) -> Result<impl Fn(&DuplicateGroup) -> bool> {
+    let prefix = match &filter.prefix {
+        Some(dir) => Some(
+            dir.canonicalize()
+                .with_context(|| format!("--prefix {}", dir.display()))?,
+        ),
+        None => None,
+    };
+    let under = match &filter.under {
+        Some(dir) => Some(
+            dir.canonicalize()
+                .with_context(|| format!("--under {}", dir.display()))?,
+        ),
+        None => None,
+    };
+    let extensions: Vec<String> = filter
+        .extensions
+        .iter()
+        .map(|e| e.trim_start_matches('.').to_lowercase())
+        .collect();
+    let min_size = filter.min_size;
+
+    Ok(move |group: &DuplicateGroup| {
+        let size = group.files.first().map_or(0, |f| f.size);
+        let extension = |path: &str| {
+            Path::new(path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+        };
+        min_size.is_none_or(|min| size >= min)
+            && prefix.as_ref().is_none_or(|p| {
+                group
+                    .files
+                    .iter()
+                    .all(|f| Path::new(&f.canonical_path).starts_with(p))
+            })
+            && (extensions.is_empty()
+                || group.files.iter().any(|f| {
+                    extension(&f.canonical_path).is_some_and(|e| extensions.contains(&e))
+                }))
+            && under.as_ref().is_none_or(|dir| {
+                group
+                    .files
+                    .iter()
+                    .any(|f| Path::new(&f.canonical_path).starts_with(dir))
+            })
+    })
+}
+
+/// A filter typed in the TUI: space-separated `min:SIZE`, `prefix:DIR`,
+/// `ext:EXT` and `under:DIR` terms, which work like the options of the same
+/// names. An empty filter lets every group through.
+fn parse_filter(text: &str) -> Result<GroupFilter> {
+    let mut filter = GroupFilter::default();
+    for term in text.split_whitespace() {
+        let Some((key, value)) = term.split_once(':') else {
+            bail!("'{}' is not a key:value filter", term);
+        };
+        match key {
+            "min" => filter.min_size = Some(tui::parse_size(value).map_err(anyhow::Error::msg)?),
+            "prefix" => filter.prefix = Some(PathBuf::from(value)),
+            "ext" => filter.extensions.push(value.to_owned()),
+            "under" => filter.under = Some(PathBuf::from(value)),
+            _ => bail!(
+                "unknown filter '{}:'; use min:, prefix:, ext: or under:",
+                key
+            ),
+        }
+    }
+    Ok(filter)
+}
+
+/// Put the groups `shown` lets through first, each part in `sort` order.
+/// Returns how many are shown.
+fn order(
+    states: &mut [GroupState],
+    shown: &dyn Fn(&DuplicateGroup) -> bool,
+    sort: GroupSort,
+) -> usize {
+    states.sort_by(|a, b| {
+        shown(&b.group)
+            .cmp(&shown(&a.group))
+            .then_with(|| compare(&a.group, &b.group, sort))
+    });
+    states.iter().filter(|gs| shown(&gs.group)).count()
+}
+
+/// Groups in `sort` order; ties keep a stable order by hash.
+fn compare(a: &DuplicateGroup, b: &DuplicateGroup, sort: GroupSort) -> Ordering {
+    fn path(g: &DuplicateGroup) -> Option<&str> {
+        g.files.iter().map(|f| f.canonical_path.as_str()).min()
+    }
+    let size = |g: &DuplicateGroup| g.files.first().map_or(0, |f| f.size);
+    match sort {
+        GroupSort::Wasted => b.reclaimable_bytes().cmp(&a.reclaimable_bytes()),
+        GroupSort::Size => size(b).cmp(&size(a)),
+        GroupSort::Copies => b.distinct_copies().cmp(&a.distinct_copies()),
+        GroupSort::Path => path(a).cmp(&path(b)),
+    }
+    .then_with(|| a.full_hash.cmp(&b.full_hash))
 }
 
 // ── Per-group action ──────────────────────────────────────────────────────────
@@ -38,87 +257,103 @@ enum FileAction {
 }
 
 struct GroupState {
-    files: Vec<FileRow>,
+    group: DuplicateGroup,
     actions: Vec<FileAction>,
     list_state: ListState,
     input_mode: Option<InputMode>,
     rule_pattern: String,
     rule_priority: String,
+    filter_input: String,
     status_msg: String,
+    /// Pattern of the rule behind the current marks; None once the user
+    /// changes them.
+    decided_by: Option<String>,
+    /// Per file: under a protected path, so never marked Delete.
+    protected: Vec<bool>,
+    /// Confirmed by the user; acted on when the review screen is committed.
+    staged: bool,
 }
 
 #[derive(Clone)]
 enum InputMode {
     RulePattern,
     RulePriority,
+    Filter,
 }
 
 impl GroupState {
-    fn new(group: &DuplicateGroup) -> Self {
+    fn new(group: &DuplicateGroup, protected: &Protected) -> Self {
         let n = group.files.len();
         let mut ls = ListState::default();
         ls.select(Some(0));
         Self {
-            files: group.files.clone(),
+            group: group.clone(),
             actions: vec![FileAction::Undecided; n],
             list_state: ls,
             input_mode: None,
             rule_pattern: String::new(),
             rule_priority: String::new(),
+            filter_input: String::new(),
             status_msg: String::new(),
+            decided_by: None,
+            protected: group
+                .files
+                .iter()
+                .map(|f| protected.contains(&f.canonical_path))
+                .collect(),
+            staged: false,
         }
     }
 
-    fn apply_rules(&mut self, rules: &[crate::db::RuleRow]) {
-        if rules.is_empty() {
-            return;
-        }
-        // Build globsets for each rule
-        let scored: Vec<i64> = self
-            .files
-            .iter()
-            .map(|f| {
-                rules
-                    .iter()
-                    .filter(|r| {
-                        Glob::new(&r.pattern)
-                            .ok()
-                            .and_then(|g| {
-                                let mut b = GlobSetBuilder::new();
-                                b.add(g);
-                                b.build().ok()
-                            })
-                            .map(|gs| gs.is_match(&f.canonical_path))
-                            .unwrap_or(false)
-                    })
-                    .map(|r| r.priority)
-                    .max()
-                    .unwrap_or(i64::MIN)
-            })
-            .collect();
-
-        // If there's a unique maximum, auto-decide
-        let max_score = scored.iter().copied().max().unwrap_or(i64::MIN);
-        let max_count = scored.iter().filter(|&&s| s == max_score).count();
-        if max_count == 1 {
-            for (i, &score) in scored.iter().enumerate() {
-                if score == max_score {
-                    self.actions[i] = FileAction::Keep;
+    fn apply_rules(&mut self, rules: &[RuleRow]) {
+        if let Some((kept, rule)) = rules::keepers(&self.group.files, rules) {
+            for (i, action) in self.actions.iter_mut().enumerate() {
+                *action = if kept.contains(&i) || self.protected[i] {
+                    FileAction::Keep
                 } else {
-                    self.actions[i] = FileAction::Delete;
-                }
+                    FileAction::Delete
+                };
             }
             self.status_msg = "Auto-resolved by priority rule.".into();
+            self.decided_by = Some(rule.describe());
         }
     }
 
+    /// The copy the others are duplicates of: the one marked Keep that is
+    /// not merely kept for being protected, if there is one.
+    fn keeper(&self) -> Option<usize> {
+        let kept = |i: &usize| self.actions[*i] == FileAction::Keep;
+        (0..self.actions.len())
+            .filter(kept)
+            .find(|&i| !self.protected[i])
+            .or_else(|| (0..self.actions.len()).find(kept))
+    }
+
+    /// Whether the marks were set by hand rather than by a rule.
+    fn is_manual(&self) -> bool {
+        self.decided_by.is_none() && self.actions.iter().any(|a| *a != FileAction::Undecided)
+    }
+
+    /// At least one copy is kept and one acted on; copies left undecided
+    /// are not touched.
     fn is_decided(&self) -> bool {
-        self.actions.iter().any(|a| *a == FileAction::Keep)
-            && self.actions.iter().any(|a| *a == FileAction::Delete)
+        self.actions.contains(&FileAction::Keep) && self.actions.contains(&FileAction::Delete)
+    }
+
+    /// Bytes the current Keep/Delete marks would actually free.
+    fn bytes_freed(&self) -> i64 {
+        let delete: Vec<usize> = self
+            .actions
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| **a == FileAction::Delete)
+            .map(|(i, _)| i)
+            .collect();
+        self.group.bytes_freed(&delete)
     }
 
     fn move_selection(&mut self, delta: i32) {
-        let len = self.files.len() as i32;
+        let len = self.group.files.len() as i32;
         let cur = self.list_state.selected().unwrap_or(0) as i32;
         self.list_state
             .select(Some((cur + delta).clamp(0, len - 1) as usize));
@@ -126,6 +361,12 @@ impl GroupState {
 
     fn mark_delete(&mut self) {
         if let Some(idx) = self.list_state.selected() {
+            if self.protected[idx] {
+                self.status_msg = "That copy is protected and cannot be deleted.".into();
+                return;
+            }
+            self.decided_by = None;
+            self.staged = false;
             self.actions[idx] = FileAction::Delete;
             // All others → keep
             for (i, a) in self.actions.iter_mut().enumerate() {
@@ -136,87 +377,389 @@ impl GroupState {
         }
     }
 
+    /// Flip the selected file between Keep and Delete (Undecided becomes
+    /// Keep), leaving the other files as they are.
+    fn toggle(&mut self) {
+        if let Some(idx) = self.list_state.selected() {
+            let next = match self.actions[idx] {
+                FileAction::Keep => FileAction::Delete,
+                FileAction::Delete | FileAction::Undecided => FileAction::Keep,
+            };
+            if next == FileAction::Delete && self.protected[idx] {
+                self.status_msg = "That copy is protected and cannot be deleted.".into();
+                return;
+            }
+            self.decided_by = None;
+            self.staged = false;
+            self.actions[idx] = next;
+        }
+    }
+
     fn mark_keep(&mut self) {
         if let Some(idx) = self.list_state.selected() {
+            self.decided_by = None;
+            self.staged = false;
             self.actions[idx] = FileAction::Keep;
-            // All others → delete
+            // All others → delete, unless protected
             for (i, a) in self.actions.iter_mut().enumerate() {
                 if i != idx {
-                    *a = FileAction::Delete;
+                    *a = if self.protected[i] {
+                        FileAction::Keep
+                    } else {
+                        FileAction::Delete
+                    };
                 }
             }
         }
     }
 }
 
+// ── Batch mode ────────────────────────────────────────────────────────────────
+
+/// Result of a `remove --batch` or `apply` run, reported through the exit
+/// status so a cron job can tell the cases apart.
+#[derive(Clone, Copy)]
+pub enum BatchOutcome {
+    /// Every group was resolved by the rules and acted on.
+    Done,
+    /// There were no duplicates to act on.
+    NothingToDo,
+    /// Some groups had no unambiguous rule decision and were skipped.
+    Unresolved,
+    /// Some files could not be removed.
+    Errors,
+}
+
+impl BatchOutcome {
+    pub fn exit_code(self) -> ExitCode {
+        ExitCode::from(match self {
+            BatchOutcome::Done => 0,
+            BatchOutcome::NothingToDo => 3,
+            BatchOutcome::Unresolved => 4,
+            BatchOutcome::Errors => 5,
+        })
+    }
+}
+
+/// Resolve every group with the priority rules alone, act on the ones they
+/// decide and skip the rest. Nothing is asked; a summary is printed.
+fn run_batch(
+    groups: &[DuplicateGroup],
+    rules: &[RuleRow],
+    opts: &RemoveOptions,
+    db: &Db,
+) -> Result<BatchOutcome> {
+    let dry = opts.action.dry_run;
+    let mut resolved = 0;
+    let mut unresolved = 0;
+    let mut files = 0;
+    let mut freed = 0;
+    let mut failures = Vec::new();
+
+    for group in groups {
+        let mut gs = GroupState::new(group, &opts.action.protected);
+        gs.apply_rules(rules);
+        // Decided, though maybe with nothing to delete when the other
+        // copies are protected.
+        if gs.decided_by.is_none() {
+            unresolved += 1;
+            continue;
+        }
+        resolved += 1;
+        // The copies the rule keeps; protected ones are only listed when
+        // nothing else is kept.
+        let mut kept: Vec<usize> = (0..group.files.len())
+            .filter(|&i| gs.actions[i] == FileAction::Keep && !gs.protected[i])
+            .collect();
+        if kept.is_empty() {
+            kept.extend(gs.keeper());
+        }
+        for i in kept {
+            println!(
+                "Keep: {}  (rule {})",
+                group.files[i].canonical_path,
+                gs.decided_by.as_deref().unwrap_or("?")
+            );
+        }
+        let done = confirm(&gs, opts, db, &mut failures);
+        for &i in &done {
+            println!(
+                "  {} {}",
+                if dry { "would remove" } else { "removed" },
+                group.files[i].canonical_path
+            );
+        }
+        files += done.len();
+        freed += group.bytes_freed(&done);
+    }
+
+    for msg in &failures {
+        eprintln!("{}", msg);
+    }
+    if resolved > 0 {
+        println!();
+    }
+    println!(
+        "{}{} of {} groups resolved by rules: {} {} files, freeing {}",
+        if dry { "[DRY RUN] " } else { "" },
+        resolved,
+        groups.len(),
+        if dry { "would remove" } else { "removed" },
+        files,
+        fmt_size(freed)
+    );
+    if unresolved > 0 {
+        println!(
+            "{} groups left unresolved (no rule decides them); run 'fdedupe remove' to review them",
+            unresolved
+        );
+    }
+    if !failures.is_empty() {
+        println!("{} files failed", failures.len());
+    }
+
+    Ok(if !failures.is_empty() {
+        BatchOutcome::Errors
+    } else if unresolved > 0 {
+        BatchOutcome::Unresolved
+    } else {
+        BatchOutcome::Done
+    })
+}
+
 // ── Main loop ─────────────────────────────────────────────────────────────────
 
+/// How the remove TUI was left.
+enum LoopEnd {
+    /// The staged groups were acted on.
+    Committed {
+        groups: usize,
+        files: usize,
+        freed: i64,
+    },
+    /// Left without acting on anything.
+    Quit { staged: usize },
+}
+
+/// Walk the groups, staging decisions in memory. Nothing is acted on until
+/// the user commits on the review screen.
 fn run_loop(
     terminal: &mut tui::Term,
-    groups: &mut Vec<DuplicateGroup>,
-    initial_rules: &[crate::db::RuleRow],
-    dry_run: bool,
+    groups: &[DuplicateGroup],
+    initial_rules: &[RuleRow],
+    opts: &RemoveOptions,
     db: &Db,
-) -> Result<()> {
-    let total = groups.len();
+    failures: &mut Vec<String>,
+) -> Result<LoopEnd> {
+    let mut current_rules: Vec<RuleRow> = initial_rules.to_vec();
+    let mut states: Vec<GroupState> = groups
+        .iter()
+        .map(|group| {
+            let mut gs = GroupState::new(group, &opts.action.protected);
+            gs.apply_rules(&current_rules);
+            gs
+        })
+        .collect();
     let mut idx = 0;
-    let mut current_rules: Vec<crate::db::RuleRow> = initial_rules.to_vec();
-
-    while idx < groups.len() {
-        let group = &groups[idx];
-        let mut gs = GroupState::new(group);
-        gs.apply_rules(&current_rules);
-
-        let result = group_loop(terminal, &mut gs, idx, total, dry_run, db, &mut current_rules)?;
+    let mut view = View {
+        sort: opts.sort,
+        preview: false,
+        filter: String::new(),
+        shown: states.len(),
+    };
+    let mut shown: Box<dyn Fn(&DuplicateGroup) -> bool> = Box::new(|_| true);
+    let mut review_msg = String::new();
 
-        match result {
-            GroupResult::Confirm => {
-                let files_to_delete: Vec<String> = gs
-                    .files
-                    .iter()
-                    .zip(gs.actions.iter())
-                    .filter(|(_, a)| **a == FileAction::Delete)
-                    .map(|(f, _)| f.canonical_path.clone())
-                    .collect();
-
-                if !dry_run {
-                    for path in &files_to_delete {
-                        if let Err(e) = std::fs::remove_file(path) {
-                            eprintln!("Failed to delete {}: {}", path, e);
-                        } else {
-                            db.delete_file_by_path(path)?;
+    loop {
+        let review = if idx == view.shown {
+            true
+        } else {
+            let rule_count = current_rules.len();
+            let result = group_loop(
+                terminal,
+                &mut states,
+                idx,
+                &mut view,
+                opts,
+                db,
+                &mut current_rules,
+            )?;
+            // A rule added inline also decides the groups not marked by hand.
+            if current_rules.len() != rule_count {
+                for gs in states.iter_mut().filter(|gs| !gs.staged && !gs.is_manual()) {
+                    gs.apply_rules(&current_rules);
+                }
+            }
+            match result {
+                GroupResult::Stage => {
+                    states[idx].staged = true;
+                    idx += 1;
+                    false
+                }
+                GroupResult::Skip => {
+                    states[idx].staged = false;
+                    idx += 1;
+                    false
+                }
+                GroupResult::Prev => {
+                    idx = idx.saturating_sub(1);
+                    false
+                }
+                GroupResult::Next => {
+                    idx = (idx + 1).min(view.shown - 1);
+                    false
+                }
+                GroupResult::Sort => {
+                    // Start over from the top in the new order.
+                    view.sort = view.sort.next();
+                    view.shown = order(&mut states, &shown, view.sort);
+                    idx = 0;
+                    states[idx].status_msg = format!("Sorted by {}.", view.sort.name());
+                    false
+                }
+                GroupResult::Filter(text) => {
+                    match parse_filter(&text).and_then(|f| filter(&f)) {
+                        Ok(keep) if !states.iter().any(|gs| keep(&gs.group)) => {
+                            states[idx].status_msg =
+                                format!("No group matches '{}'; the filter is unchanged.", text);
+                        }
+                        Ok(keep) => {
+                            // Start over from the top with the groups let through.
+                            shown = Box::new(keep);
+                            view.filter = text;
+                            view.shown = order(&mut states, &shown, view.sort);
+                            idx = 0;
+                            states[idx].status_msg =
+                                format!("Showing {} of {} groups.", view.shown, states.len());
                         }
+                        Err(e) => states[idx].status_msg = format!("{:#}", e),
                     }
+                    false
                 }
-                idx += 1;
+                GroupResult::Review => true,
+                GroupResult::Quit if states.iter().any(|gs| gs.staged) => {
+                    review_msg =
+                        "Commit the staged groups with c, or press q again to discard them.".into();
+                    true
+                }
+                GroupResult::Quit => return Ok(LoopEnd::Quit { staged: 0 }),
+            }
+        };
+        if !review {
+            continue;
+        }
+
+        match review_loop(terminal, &states, opts, &review_msg)? {
+            ReviewResult::Commit => {
+                let (mut groups, mut files, mut freed) = (0, 0, 0);
+                for gs in states.iter().filter(|gs| gs.staged) {
+                    let done = confirm(gs, opts, db, failures);
+                    groups += 1;
+                    files += done.len();
+                    freed += gs.group.bytes_freed(&done);
+                }
+                return Ok(LoopEnd::Committed {
+                    groups,
+                    files,
+                    freed,
+                });
+            }
+            ReviewResult::Back => {
+                idx = idx.min(view.shown - 1);
+                review_msg.clear();
+            }
+            ReviewResult::Quit => {
+                return Ok(LoopEnd::Quit {
+                    staged: states.iter().filter(|gs| gs.staged).count(),
+                })
             }
-            GroupResult::Skip => {
-                idx += 1;
+        }
+    }
+}
+
+/// Hand every file of a decided group marked Delete to `action::apply`.
+/// Returns the indexes of the files acted on; failures are appended to
+/// `failures`.
+fn confirm(
+    gs: &GroupState,
+    opts: &RemoveOptions,
+    db: &Db,
+    failures: &mut Vec<String>,
+) -> Vec<usize> {
+    let mut done = Vec::new();
+    let Some(keep) = gs.keeper().map(|i| &gs.group.files[i]) else {
+        return done;
+    };
+    for (i, target) in gs.group.files.iter().enumerate() {
+        if gs.actions[i] != FileAction::Delete {
+            continue;
+        }
+        match action::apply(&opts.action, keep, target, gs.decided_by.as_deref(), db) {
+            Ok(()) => done.push(i),
+            Err(e) => {
+                failures.push(format!("Failed: {:#}", e));
+                if e.is::<KeptFileChanged>() {
+                    break;
+                }
             }
-            GroupResult::Quit => break,
         }
     }
+    done
+}
 
-    Ok(())
+/// How the groups are shown; kept while moving between them.
+struct View {
+    sort: GroupSort,
+    /// Show the preview pane next to the file list.
+    preview: bool,
+    /// Filter typed with `f`, empty for none.
+    filter: String,
+    /// Groups the filter lets through; they come first in `states`.
+    shown: usize,
 }
 
+/// How often the preview of the selected file is read again.
+const PREVIEW_REFRESH: Duration = Duration::from_secs(1);
+
 enum GroupResult {
-    Confirm,
+    Stage,
     Skip,
+    Prev,
+    Next,
+    Sort,
+    /// Show only the groups the typed filter lets through.
+    Filter(String),
+    Review,
     Quit,
 }
 
 fn group_loop(
     terminal: &mut tui::Term,
-    gs: &mut GroupState,
+    states: &mut [GroupState],
     group_idx: usize,
-    total: usize,
-    dry_run: bool,
+    view: &mut View,
+    opts: &RemoveOptions,
     db: &Db,
-    rules: &mut Vec<crate::db::RuleRow>,
+    rules: &mut Vec<RuleRow>,
 ) -> Result<GroupResult> {
+    let total = view.shown;
+    let staged = states.iter().filter(|gs| gs.staged).count();
+    let gs = &mut states[group_idx];
+    // Selected file, when it was read, and its preview.
+    let mut preview: Option<(usize, Instant, Vec<Line<'static>>)> = None;
     loop {
-        let size_each = gs.files.first().map(|f| f.size).unwrap_or(0);
+        let size_each = gs.group.files.first().map(|f| f.size).unwrap_or(0);
+        let copies = gs.group.distinct_copies();
+        let freed = gs.bytes_freed();
+        let selected = gs.list_state.selected().unwrap_or(0);
+        if view.preview
+            && preview
+                .as_ref()
+                .is_none_or(|(i, at, _)| *i != selected || at.elapsed() >= PREVIEW_REFRESH)
+        {
+            let lines = preview::preview(&gs.group.files[selected]);
+            preview = Some((selected, Instant::now(), lines));
+        }
 
         terminal.draw(|f| {
             let area = f.area();
@@ -230,13 +773,26 @@ fn group_loop(
                 .split(area);
 
             // Header
-            let dry_tag = if dry_run { " [DRY RUN]" } else { "" };
+            let dry_tag = if opts.action.dry_run { " [DRY RUN]" } else { "" };
+            let staged_tag = if gs.staged { " [STAGED]" } else { "" };
+            let filter_tag = if view.filter.is_empty() {
+                String::new()
+            } else {
+                format!(" matching '{}'", view.filter)
+            };
             let title = format!(
-                " fdedupe — remove{}  (group {} of {}, {} each) ",
+                " fdedupe — remove{}{}  (group {} of {}{} by {}, {} each, {} separate copies, \
+                 frees {}; {} staged) ",
                 dry_tag,
+                staged_tag,
                 group_idx + 1,
                 total,
-                fmt_size(size_each)
+                filter_tag,
+                view.sort.name(),
+                fmt_size(size_each),
+                copies,
+                fmt_size(freed),
+                staged
             );
             let header = Paragraph::new(Line::from(gs.status_msg.as_str()))
                 .block(Block::default().borders(Borders::ALL).title(title));
@@ -244,38 +800,78 @@ fn group_loop(
 
             // File list
             let items: Vec<ListItem> = gs
+                .group
                 .files
                 .iter()
                 .zip(gs.actions.iter())
                 .map(|(file, action)| {
+                    let linked = gs
+                        .group
+                        .files
+                        .iter()
+                        .any(|other| other.id != file.id && file.shares_inode_with(other));
                     let (marker, style) = match action {
                         FileAction::Keep => (
                             "[KEEP]   ",
                             Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
                         ),
                         FileAction::Delete => (
-                            "[DELETE] ",
+                            delete_marker(opts.action.action),
                             Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
                         ),
                         FileAction::Undecided => ("[?]      ", Style::default()),
                     };
-                    ListItem::new(Line::from(vec![
-                        Span::styled(marker, style),
-                        Span::raw(&file.canonical_path),
-                    ]))
+                    let mut spans = vec![Span::styled(marker, style)];
+                    if opts.action.protected.contains(&file.canonical_path) {
+                        spans.push(Span::styled("🔒 ", Style::default().fg(Color::Yellow)));
+                    }
+                    spans.push(Span::raw(&file.canonical_path));
+                    if linked {
+                        spans.push(Span::styled(
+                            "  (hard link)",
+                            Style::default().fg(Color::Cyan),
+                        ));
+                    }
+                    ListItem::new(Line::from(spans))
                 })
                 .collect();
 
             let list = List::new(items)
                 .block(Block::default().borders(Borders::ALL).title(" Files "))
                 .highlight_style(Style::default().bg(Color::DarkGray));
-            f.render_stateful_widget(list, chunks[1], &mut gs.list_state);
+            let (list_area, preview_area) = if view.preview {
+                let halves = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(chunks[1]);
+                (halves[0], Some(halves[1]))
+            } else {
+                (chunks[1], None)
+            };
+            f.render_stateful_widget(list, list_area, &mut gs.list_state);
+            if let (Some(area), Some((_, _, lines))) = (preview_area, &preview) {
+                let pane = Paragraph::new(lines.clone())
+                    .block(Block::default().borders(Borders::ALL).title(" Preview "))
+                    .wrap(Wrap { trim: false });
+                f.render_widget(pane, area);
+            }
 
             // Footer / input
             if let Some(ref mode) = gs.input_mode {
-                let (prompt, value) = match mode {
-                    InputMode::RulePattern => ("Glob pattern: ", gs.rule_pattern.as_str()),
-                    InputMode::RulePriority => ("Priority (integer): ", gs.rule_priority.as_str()),
+                let (title, prompt, value) = match mode {
+                    InputMode::RulePattern => {
+                        (" Add Rule ", "Glob pattern: ", gs.rule_pattern.as_str())
+                    }
+                    InputMode::RulePriority => (
+                        " Add Rule ",
+                        "Priority (integer): ",
+                        gs.rule_priority.as_str(),
+                    ),
+                    InputMode::Filter => (
+                        " Filter ",
+                        "min:SIZE ext:EXT prefix:DIR under:DIR (empty for all): ",
+                        gs.filter_input.as_str(),
+                    ),
                 };
                 let input_text = vec![
                     Line::from(Span::raw(format!("{}{}_", prompt, value))),
@@ -285,12 +881,18 @@ fn group_loop(
                     )),
                 ];
                 let input = Paragraph::new(input_text)
-                    .block(Block::default().borders(Borders::ALL).title(" Add Rule "));
+                    .block(Block::default().borders(Borders::ALL).title(title));
                 f.render_widget(input, chunks[2]);
             } else {
-                let footer = Paragraph::new(Line::from(
-                    "  ↑↓ select   k keep   d/Enter delete   r add rule   s skip   q quit",
-                ))
+                let footer = Paragraph::new(vec![
+                    Line::from(
+                        "  ↑↓ select   k keep   d/Enter delete   t toggle   r add rule   o sort   \
+                         f filter   p preview",
+                    ),
+                    Line::from(
+                        "  Space/Enter stage   s skip   ←→ previous/next group   v review   q quit",
+                    ),
+                ])
                 .style(Style::default().fg(Color::DarkGray));
                 f.render_widget(footer, chunks[2]);
             }
@@ -304,6 +906,7 @@ fn group_loop(
                         gs.input_mode = None;
                         gs.rule_pattern.clear();
                         gs.rule_priority.clear();
+                        gs.filter_input.clear();
                     }
                     KeyCode::Enter => match mode {
                         InputMode::RulePattern => {
@@ -313,12 +916,16 @@ fn group_loop(
                         }
                         InputMode::RulePriority => {
                             let priority: i64 = gs.rule_priority.parse().unwrap_or(0);
-                            db.insert_rule(&gs.rule_pattern, priority)?;
-                            let new_rule = crate::db::RuleRow {
+                            let mut new_rule = RuleRow {
                                 id: 0,
+                                kind: RuleKind::Glob,
                                 pattern: gs.rule_pattern.clone(),
                                 priority,
+                                scope: None,
+                                scope_match: ScopeMatch::default(),
+                                keep: 1,
                             };
+                            new_rule.id = db.insert_rule(&new_rule)?;
                             rules.push(new_rule);
                             gs.status_msg =
                                 format!("Rule added: {} (priority {})", gs.rule_pattern, priority);
@@ -327,6 +934,11 @@ fn group_loop(
                             gs.rule_priority.clear();
                             gs.apply_rules(rules);
                         }
+                        InputMode::Filter => {
+                            gs.input_mode = None;
+                            let text = std::mem::take(&mut gs.filter_input);
+                            return Ok(GroupResult::Filter(text.trim().to_owned()));
+                        }
                     },
                     KeyCode::Backspace => {
                         match mode {
@@ -336,6 +948,9 @@ fn group_loop(
                             InputMode::RulePriority => {
                                 gs.rule_priority.pop();
                             }
+                            InputMode::Filter => {
+                                gs.filter_input.pop();
+                            }
                         }
                     }
                     KeyCode::Char(c) => match mode {
@@ -345,6 +960,7 @@ fn group_loop(
                                 gs.rule_priority.push(c);
                             }
                         }
+                        InputMode::Filter => gs.filter_input.push(c),
                     },
                     _ => {}
                 }
@@ -355,21 +971,135 @@ fn group_loop(
             match key.code {
                 KeyCode::Char('q') | KeyCode::Esc => return Ok(GroupResult::Quit),
                 KeyCode::Char('s') => return Ok(GroupResult::Skip),
+                KeyCode::Left => return Ok(GroupResult::Prev),
+                KeyCode::Right => return Ok(GroupResult::Next),
+                KeyCode::Char('o') => return Ok(GroupResult::Sort),
+                KeyCode::Char('f') => {
+                    gs.filter_input = view.filter.clone();
+                    gs.input_mode = Some(InputMode::Filter);
+                }
+                KeyCode::Char('p') => {
+                    view.preview = !view.preview;
+                    preview = None;
+                }
+                KeyCode::Char('v') => return Ok(GroupResult::Review),
                 KeyCode::Up => gs.move_selection(-1),
                 KeyCode::Down => gs.move_selection(1),
                 KeyCode::Char('k') => gs.mark_keep(),
-                KeyCode::Char('d') | KeyCode::Enter => {
-                    if gs.input_mode.is_none() {
-                        if key.code == KeyCode::Enter && gs.is_decided() {
-                            return Ok(GroupResult::Confirm);
-                        }
-                        gs.mark_delete();
-                    }
-                }
+                KeyCode::Char('t') => gs.toggle(),
+                KeyCode::Enter if gs.is_decided() => return Ok(GroupResult::Stage),
+                KeyCode::Char('d') | KeyCode::Enter => gs.mark_delete(),
                 KeyCode::Char('r') => {
                     gs.input_mode = Some(InputMode::RulePattern);
                 }
-                KeyCode::Char(' ') if gs.is_decided() => return Ok(GroupResult::Confirm),
+                KeyCode::Char(' ') if gs.is_decided() => return Ok(GroupResult::Stage),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Marker of a file marked Delete, after the action it will get.
+fn delete_marker(action: RemoveAction) -> &'static str {
+    match action {
+        RemoveAction::Trash => "[TRASH]  ",
+        RemoveAction::Delete => "[DELETE] ",
+        RemoveAction::Hardlink => "[LINK]   ",
+        RemoveAction::Symlink => "[SYMLN]  ",
+        RemoveAction::Quarantine => "[QUAR]   ",
+    }
+}
+
+// ── Review screen ─────────────────────────────────────────────────────────────
+
+enum ReviewResult {
+    Commit,
+    Back,
+    Quit,
+}
+
+/// Every pending action of the staged groups, with the space they free.
+fn review_loop(
+    terminal: &mut tui::Term,
+    states: &[GroupState],
+    opts: &RemoveOptions,
+    status_msg: &str,
+) -> Result<ReviewResult> {
+    let staged: Vec<&GroupState> = states.iter().filter(|gs| gs.staged).collect();
+    let files: usize = staged
+        .iter()
+        .map(|gs| gs.actions.iter().filter(|a| **a == FileAction::Delete).count())
+        .sum();
+    let freed: i64 = staged.iter().map(|gs| gs.bytes_freed()).sum();
+
+    let mut lines: Vec<ListItem> = Vec::new();
+    for gs in &staged {
+        for (file, action) in gs.group.files.iter().zip(&gs.actions) {
+            let (marker, style) = match action {
+                FileAction::Keep => ("[KEEP]   ", Style::default().fg(Color::Green)),
+                FileAction::Delete => (
+                    delete_marker(opts.action.action),
+                    Style::default().fg(Color::Red),
+                ),
+                FileAction::Undecided => ("[?]      ", Style::default()),
+            };
+            lines.push(ListItem::new(Line::from(vec![
+                Span::styled(marker, style),
+                Span::raw(format!("{}  ({})", file.canonical_path, fmt_size(file.size))),
+            ])));
+        }
+        lines.push(ListItem::new(""));
+    }
+    if staged.is_empty() {
+        lines.push(ListItem::new("  Nothing staged yet: confirm groups with Space or Enter."));
+    }
+    let mut list_state = ListState::default();
+    list_state.select(Some(0));
+
+    loop {
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(1),
+                ])
+                .split(f.area());
+
+            let dry_tag = if opts.action.dry_run { " [DRY RUN]" } else { "" };
+            let title = format!(
+                " fdedupe — review{}  ({} files in {} of {} groups, frees {}) ",
+                dry_tag,
+                files,
+                staged.len(),
+                states.len(),
+                fmt_size(freed)
+            );
+            let header = Paragraph::new(Line::from(status_msg))
+                .block(Block::default().borders(Borders::ALL).title(title));
+            f.render_widget(header, chunks[0]);
+
+            let list = List::new(lines.clone())
+                .block(Block::default().borders(Borders::ALL).title(" Pending "))
+                .highlight_style(Style::default().bg(Color::DarkGray));
+            f.render_stateful_widget(list, chunks[1], &mut list_state);
+
+            let footer = Paragraph::new(Line::from(
+                "  c commit   ↑↓ scroll   Esc/← back to the groups   q quit without changes",
+            ))
+            .style(Style::default().fg(Color::DarkGray));
+            f.render_widget(footer, chunks[2]);
+        })?;
+
+        if let Some(key) = tui::next_key(Duration::from_millis(100))? {
+            let cur = list_state.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Char('c') if !staged.is_empty() => return Ok(ReviewResult::Commit),
+                KeyCode::Esc | KeyCode::Left => return Ok(ReviewResult::Back),
+                KeyCode::Char('q') => return Ok(ReviewResult::Quit),
+                KeyCode::Up => list_state.select(Some(cur.saturating_sub(1))),
+                KeyCode::Down => list_state.select(Some((cur + 1).min(lines.len() - 1))),
                 _ => {}
             }
         }
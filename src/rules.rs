@@ -0,0 +1,498 @@
+use anyhow::{bail, Context, Result};
+use globset::Glob;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::Path;
+
+use crate::cli::{RuleKind, RulesArgs, RulesCommand, ScopeMatch};
+use crate::config::{Config, RuleSpec};
+use crate::db::{Db, FileRow, RuleRow};
+
+/// A file written by `rules export`; the same shape as the `rules` key of
+/// the config file.
+#[derive(Serialize, Deserialize)]
+struct RulesFile {
+    rules: Vec<RuleSpec>,
+}
+
+pub fn run(args: &RulesArgs, config: &Config, db: &Db) -> Result<()> {
+    match &args.command {
+        RulesCommand::List => list(config, db),
+        RulesCommand::Add {
+            pattern,
+            kind,
+            priority,
+            scope,
+            scope_match,
+            keep,
+        } => {
+            let mut rule = RuleRow {
+                id: 0,
+                kind: *kind,
+                pattern: pattern.clone().unwrap_or_default(),
+                priority: *priority,
+                scope: scope.as_deref().map(scope_path).transpose()?,
+                scope_match: *scope_match,
+                keep: *keep,
+            };
+            check_rule(rule.kind, &rule.pattern)?;
+            rule.id = db.insert_rule(&rule)?;
+            println!(
+                "Added rule #{}: {} (priority {})",
+                rule.id,
+                rule.describe(),
+                rule.priority
+            );
+            Ok(())
+        }
+        RulesCommand::Rm { ids } => {
+            let mut missing = Vec::new();
+            for &id in ids {
+                if db.delete_rule(id)? {
+                    println!("Removed rule #{}", id);
+                } else {
+                    missing.push(id.to_string());
+                }
+            }
+            if !missing.is_empty() {
+                bail!("no rule with id {}", missing.join(", "));
+            }
+            Ok(())
+        }
+        RulesCommand::Edit {
+            id,
+            kind,
+            pattern,
+            priority,
+            scope,
+            no_scope,
+            scope_match,
+            keep,
+        } => {
+            if kind.is_none()
+                && pattern.is_none()
+                && priority.is_none()
+                && scope.is_none()
+                && !no_scope
+                && scope_match.is_none()
+                && keep.is_none()
+            {
+                bail!("nothing to change; see 'fdedupe rules edit --help'");
+            }
+            let mut rule = db
+                .get_rule(*id)?
+                .with_context(|| format!("no rule with id {}", id))?;
+            rule.kind = kind.unwrap_or(rule.kind);
+            if let Some(p) = pattern {
+                rule.pattern = p.clone();
+            } else if !rule.kind.takes_pattern() {
+                rule.pattern.clear();
+            }
+            rule.priority = priority.unwrap_or(rule.priority);
+            if *no_scope {
+                rule.scope = None;
+            } else if let Some(dir) = scope {
+                rule.scope = Some(scope_path(dir)?);
+            }
+            rule.scope_match = scope_match.unwrap_or(rule.scope_match);
+            rule.keep = keep.unwrap_or(rule.keep);
+            check_rule(rule.kind, &rule.pattern)?;
+            db.update_rule(&rule)?;
+            println!(
+                "Rule #{}: {} (priority {})",
+                id,
+                rule.describe(),
+                rule.priority
+            );
+            Ok(())
+        }
+        RulesCommand::Test { path } => test(path, config, db),
+        RulesCommand::Export { output } => export(output.as_deref(), db),
+        RulesCommand::Import { file, replace } => import(file, *replace, db),
+    }
+}
+
+/// The rules `remove`, `plan`, `link` and `dedupe` apply: the database's,
+/// then those of the config file that the database does not already have.
+/// Config rules have id 0, so at equal priority they come first, in the
+/// order they are written.
+pub fn load(config: &Config, db: &Db) -> Result<Vec<RuleRow>> {
+    let mut rules = db.all_rules()?;
+    let stored: Vec<RuleSpec> = rules.iter().map(spec).collect();
+    for (i, s) in config.rules.iter().enumerate() {
+        let rule = from_spec(s).with_context(|| format!("rule {} in the config file", i + 1))?;
+        if !stored.contains(&spec(&rule)) {
+            rules.push(rule);
+        }
+    }
+    Ok(rules)
+}
+
+fn spec(rule: &RuleRow) -> RuleSpec {
+    RuleSpec {
+        kind: rule.kind,
+        pattern: rule.pattern.clone(),
+        priority: rule.priority,
+        scope: rule.scope.clone(),
+        scope_match: rule.scope_match,
+        keep: rule.keep,
+    }
+}
+
+/// A checked rule with id 0; the scope is made absolute, canonical if it
+/// exists.
+fn from_spec(spec: &RuleSpec) -> Result<RuleRow> {
+    check_rule(spec.kind, &spec.pattern)?;
+    if spec.keep == 0 {
+        bail!("keep must be at least 1");
+    }
+    let scope = match &spec.scope {
+        Some(dir) => {
+            let dir = Path::new(dir);
+            let dir = dir.canonicalize().or_else(|_| std::path::absolute(dir))?;
+            Some(dir.to_string_lossy().into_owned())
+        }
+        None => None,
+    };
+    Ok(RuleRow {
+        id: 0,
+        kind: spec.kind,
+        pattern: spec.pattern.clone(),
+        priority: spec.priority,
+        scope,
+        scope_match: spec.scope_match,
+        keep: spec.keep,
+    })
+}
+
+/// `#id`, or `config` for a rule from the config file.
+fn label(rule: &RuleRow) -> String {
+    match rule.id {
+        0 => "config".to_owned(),
+        id => format!("#{}", id),
+    }
+}
+
+// ── Ranking ──────────────────────────────────────────────────────────────────
+
+/// Indexes of the files the rules keep, and the rule that decided them.
+///
+/// Only rules in scope for the group take part (see `in_scope`). They apply
+/// one after the other, highest priority first and equal priorities in id
+/// order. Each narrows the candidates — at first every file — to the ones it
+/// prefers: those it matches (`glob`, `regex`, `name`), those it does not
+/// (`avoid-name`), or those with the `keep` best values (`newest`,
+/// `shortest-path`, ...). A rule preferring all or none of the candidates
+/// changes nothing. The rule that leaves no more candidates than its `keep`
+/// count decides, and they are all kept; when the rules run out first, the
+/// group stays undecided rather than being broken arbitrarily.
+pub fn keepers<'r>(files: &[FileRow], rules: &'r [RuleRow]) -> Option<(Vec<usize>, &'r RuleRow)> {
+    let mut ordered: Vec<&RuleRow> = rules.iter().filter(|r| in_scope(r, files)).collect();
+    ordered.sort_by_key(|r| (Reverse(r.priority), r.id));
+
+    let mut candidates: Vec<usize> = (0..files.len()).collect();
+    for rule in ordered {
+        let preferred = prefer(rule, files, &candidates);
+        if preferred.is_empty() || preferred.len() == candidates.len() {
+            continue;
+        }
+        candidates = preferred;
+        if candidates.len() <= rule.keep as usize {
+            return Some((candidates, rule));
+        }
+    }
+    None
+}
+
+/// Whether `rule` applies to a group of `files`: always for a rule without
+/// scope, otherwise when all (or, with `scope_match: any`, at least one) of
+/// the files are under the scope directory.
+pub fn in_scope(rule: &RuleRow, files: &[FileRow]) -> bool {
+    let Some(scope) = &rule.scope else {
+        return true;
+    };
+    let under = |f: &FileRow| Path::new(&f.canonical_path).starts_with(scope);
+    match rule.scope_match {
+        ScopeMatch::All => files.iter().all(under),
+        ScopeMatch::Any => files.iter().any(under),
+    }
+}
+
+/// The candidates `rule` prefers.
+fn prefer(rule: &RuleRow, files: &[FileRow], candidates: &[usize]) -> Vec<usize> {
+    let path = |i: usize| files[i].canonical_path.as_str();
+    let n = rule.keep as usize;
+    match rule.kind {
+        RuleKind::Glob | RuleKind::Regex | RuleKind::Name | RuleKind::AvoidName => candidates
+            .iter()
+            .copied()
+            .filter(|&i| matches(rule, path(i)) == Some(true))
+            .collect(),
+        RuleKind::Newest => best_by(candidates, n, |i| Reverse(files[i].modified_at)),
+        RuleKind::Oldest => best_by(candidates, n, |i| files[i].modified_at),
+        RuleKind::ShortestPath => best_by(candidates, n, |i| path(i).chars().count()),
+        RuleKind::Shallowest => best_by(candidates, n, |i| Path::new(path(i)).components().count()),
+        RuleKind::SmallestOnDisk => best_by(candidates, n, |i| disk_usage(path(i))),
+    }
+}
+
+/// The candidates with the `n` lowest values of `key`, and any tied with
+/// the last of them.
+fn best_by<K: Ord>(candidates: &[usize], n: usize, key: impl Fn(usize) -> K) -> Vec<usize> {
+    let keys: Vec<K> = candidates.iter().map(|&i| key(i)).collect();
+    let mut sorted: Vec<&K> = keys.iter().collect();
+    sorted.sort();
+    let Some(&cutoff) = sorted.get(n.min(sorted.len()).saturating_sub(1)) else {
+        return Vec::new();
+    };
+    candidates
+        .iter()
+        .zip(&keys)
+        .filter(|(_, k)| *k <= cutoff)
+        .map(|(&i, _)| i)
+        .collect()
+}
+
+/// Whether a pattern rule prefers the file at canonical path `path`; None
+/// for the kinds that order copies instead. A pattern that does not compile
+/// matches nothing.
+pub fn matches(rule: &RuleRow, path: &str) -> Option<bool> {
+    let name = || {
+        Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let glob = |s: &str| {
+        Glob::new(&rule.pattern)
+            .map(|g| g.compile_matcher().is_match(s))
+            .unwrap_or(false)
+    };
+    match rule.kind {
+        RuleKind::Glob => Some(glob(path)),
+        RuleKind::Regex => Some(
+            Regex::new(&rule.pattern)
+                .map(|re| re.is_match(path))
+                .unwrap_or(false),
+        ),
+        RuleKind::Name => Some(glob(&name())),
+        RuleKind::AvoidName => Some(
+            Glob::new(&rule.pattern)
+                .map(|g| !g.compile_matcher().is_match(name()))
+                .unwrap_or(false),
+        ),
+        _ => None,
+    }
+}
+
+/// Bytes allocated to the file; unreadable files sort last.
+#[cfg(unix)]
+fn disk_usage(path: &str) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).map_or(u64::MAX, |m| m.blocks() * 512)
+}
+
+#[cfg(not(unix))]
+fn disk_usage(path: &str) -> u64 {
+    std::fs::metadata(path).map_or(u64::MAX, |m| m.len())
+}
+
+/// Canonical form of a `--scope` directory, as stored in the rule.
+fn scope_path(dir: &Path) -> Result<String> {
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("scope {}", dir.display()))?;
+    if !dir.is_dir() {
+        bail!("scope {} is not a directory", dir.display());
+    }
+    Ok(dir.to_string_lossy().into_owned())
+}
+
+fn check_rule(kind: RuleKind, pattern: &str) -> Result<()> {
+    if !kind.takes_pattern() {
+        if !pattern.is_empty() {
+            bail!("rules of kind {} take no pattern", kind.name());
+        }
+        return Ok(());
+    }
+    if pattern.is_empty() {
+        bail!("rules of kind {} need a pattern", kind.name());
+    }
+    if kind == RuleKind::Regex {
+        Regex::new(pattern).with_context(|| format!("invalid regex '{}'", pattern))?;
+    } else {
+        Glob::new(pattern).with_context(|| format!("invalid glob '{}'", pattern))?;
+    }
+    Ok(())
+}
+
+// ── Commands ─────────────────────────────────────────────────────────────────
+
+fn list(config: &Config, db: &Db) -> Result<()> {
+    let mut rules = load(config, db)?;
+    if rules.is_empty() {
+        println!("No rules. Add one with 'fdedupe rules add <glob> --priority <N>'.");
+        return Ok(());
+    }
+    rules.sort_by_key(|r| (Reverse(r.priority), r.id));
+    println!(
+        "{:>6}  {:>8}  {:<16}  {:<24}  {:>4}  scope",
+        "id", "priority", "kind", "pattern", "keep"
+    );
+    for r in &rules {
+        let scope = match (&r.scope, r.scope_match) {
+            (None, _) => String::new(),
+            (Some(dir), ScopeMatch::All) => dir.clone(),
+            (Some(dir), ScopeMatch::Any) => format!("{} (any)", dir),
+        };
+        let id = match r.id {
+            0 => "config".to_owned(),
+            id => id.to_string(),
+        };
+        println!(
+            "{:>6}  {:>8}  {:<16}  {:<24}  {:>4}  {}",
+            id,
+            r.priority,
+            r.kind.name(),
+            r.pattern,
+            r.keep,
+            scope
+        );
+    }
+    Ok(())
+}
+
+/// Write the database's rules as YAML to `output`, or stdout.
+fn export(output: Option<&Path>, db: &Db) -> Result<()> {
+    let file = RulesFile {
+        rules: db.all_rules()?.iter().map(spec).collect(),
+    };
+    let text = serde_yaml::to_string(&file)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, text).with_context(|| format!("writing {}", path.display()))?;
+            eprintln!("Wrote {} rules to {}", file.rules.len(), path.display());
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Add the rules of `path` to the database, skipping those it already has;
+/// with `replace`, delete the existing rules first. Every rule is checked
+/// before anything is written.
+fn import(path: &Path, replace: bool, db: &Db) -> Result<()> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let file: RulesFile =
+        serde_yaml::from_str(&text).with_context(|| format!("parsing {}", path.display()))?;
+    let rules = file
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(i, s)| from_spec(s).with_context(|| format!("rule {} in {}", i + 1, path.display())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let tx = db.transaction()?;
+    let removed = if replace { db.delete_all_rules()? } else { 0 };
+    let mut present: Vec<RuleSpec> = db.all_rules()?.iter().map(spec).collect();
+    let mut added = 0;
+    for rule in &rules {
+        if present.contains(&spec(rule)) {
+            continue;
+        }
+        db.insert_rule(rule)?;
+        present.push(spec(rule));
+        added += 1;
+    }
+    tx.commit()?;
+
+    if replace {
+        println!("Removed {} rules", removed);
+    }
+    println!(
+        "Imported {} rules from {} ({} already present)",
+        added,
+        path.display(),
+        rules.len() - added
+    );
+    Ok(())
+}
+
+/// Print the rules matching `path`, highest priority first, and what they
+/// decide for its duplicate group if it has one.
+fn test(path: &Path, config: &Config, db: &Db) -> Result<()> {
+    let path = path.canonicalize().or_else(|_| std::path::absolute(path))?;
+    let path = path.to_string_lossy();
+    let mut rules = load(config, db)?;
+    rules.sort_by_key(|r| (Reverse(r.priority), r.id));
+    let in_scope = |r: &&RuleRow| {
+        r.scope
+            .as_ref()
+            .is_none_or(|s| Path::new(&*path).starts_with(s))
+    };
+    let matching: Vec<&RuleRow> = rules
+        .iter()
+        .filter(in_scope)
+        .filter(|r| matches(r, &path) == Some(true))
+        .collect();
+    if matching.is_empty() {
+        println!("No pattern rule matches {}", path);
+    } else {
+        println!("Rules matching {}:", path);
+        for r in &matching {
+            println!(
+                "  {:<6} priority {:>6}  {}",
+                label(r),
+                r.priority,
+                r.describe()
+            );
+        }
+    }
+    let ordering: Vec<String> = rules
+        .iter()
+        .filter(|r| !r.kind.takes_pattern())
+        .filter(in_scope)
+        .map(|r| format!("{} {}", label(r), r.describe()))
+        .collect();
+    if !ordering.is_empty() {
+        println!(
+            "Ordering rules in scope (apply to whole groups): {}",
+            ordering.join(", ")
+        );
+    }
+
+    let Some(hash) = db.file_by_path(&path)?.and_then(|f| f.full_hash) else {
+        return Ok(());
+    };
+    let files = db.files_with_full_hash(&hash)?;
+    if files.len() < 2 {
+        return Ok(());
+    }
+    println!();
+    match keepers(&files, &rules) {
+        Some((kept, rule)) if kept.iter().any(|&i| files[i].canonical_path == path) => {
+            println!(
+                "Kept among its {} copies (rule {} {})",
+                files.len(),
+                label(rule),
+                rule.describe()
+            );
+        }
+        Some((kept, rule)) => println!(
+            "Removed in favour of {} (rule {} {})",
+            kept.iter()
+                .map(|&i| files[i].canonical_path.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            label(rule),
+            rule.describe()
+        ),
+        None => println!(
+            "Its {} copies are not decided by the rules (no rule singles out which to keep)",
+            files.len()
+        ),
+    }
+    Ok(())
+}
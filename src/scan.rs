@@ -1,14 +1,23 @@
 use anyhow::Result;
 use globset::{Glob, GlobSet, GlobSetBuilder};
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use crate::cli::ScanArgs;
+use crate::cli::{FullHashScope, ScanArgs};
 use crate::config::Config;
-use crate::db::Db;
+use crate::db::{Db, FileMeta, WriteBatch};
 use crate::hash;
-use crate::scan_tui::ScanProgress;
+use crate::pool::Pool;
+use crate::scan_tui::{ScanProgress, Stage};
+
+/// How often the stage load display is refreshed while waiting for results.
+const LOAD_REFRESH: Duration = Duration::from_millis(100);
+
+/// Rows written per transaction when neither CLI nor config sets a batch size.
+const DEFAULT_BATCH_SIZE: usize = 1000;
 
 pub struct ScanOptions {
     pub recursive: bool,
@@ -17,14 +26,26 @@ pub struct ScanOptions {
     pub hidden: bool,
     pub include: GlobSet,
     pub exclude: GlobSet,
+    pub jobs: usize,
+    pub batch_size: usize,
+    pub full_hash_scope: FullHashScope,
 }
-
 impl ScanOptions {
     pub fn from_args_and_config(args: &ScanArgs, config: &Config) -> Result<Self> {
         let recursive = args.recursive || config.recursive;
         let rescan = args.rescan || config.rescan;
         let follow_symlinks = args.follow_symlinks || config.follow_symlinks;
         let hidden = args.hidden || config.hidden;
+        let jobs = args
+            .jobs
+            .or(config.jobs)
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .max(1);
+        let batch_size = args
+            .batch_size
+            .or(config.batch_size)
+            .unwrap_or(DEFAULT_BATCH_SIZE)
+            .max(1);
 
         // CLI include/exclude take priority; fall back to config
         let include_globs: Vec<&str> = if !args.include.is_empty() {
@@ -45,6 +66,12 @@ impl ScanOptions {
             hidden,
             include: build_globset(&include_globs)?,
             exclude: build_globset(&exclude_globs)?,
+            jobs,
+            batch_size,
+            full_hash_scope: args
+                .full_hash_scope
+                .or(config.full_hash_scope)
+                .unwrap_or_default(),
         })
     }
 
@@ -63,8 +90,62 @@ impl ScanOptions {
     }
 }
 
+// ── Pipeline jobs ───────────────────────────────────────────────────────────
+
+/// Read a directory. `subdirs_only` is set for directories that were already
+/// scanned and are only traversed to reach their children.
+struct EnumerateJob {
+    dir: PathBuf,
+    dir_id: i64,
+    subdirs_only: bool,
+}
+
+struct FastHashJob {
+    dir_id: i64,
+    file: FsFile,
+}
+
+/// Full hash of one inode; `ids` are all the rows that are links to it.
+struct FullHashJob {
+    ids: Vec<i64>,
+    path: PathBuf,
+}
+
+enum Outcome {
+    Listed(EnumerateJob, Result<Listing>),
+    FastHashed(FastHashJob, Result<String>),
+    FullHashed(FullHashJob, Result<String>),
+}
+
+/// A regular file or unfollowed symlink found on disk, with the metadata
+/// compared against the DB.
+struct FsFile {
+    name: String,
+    path: PathBuf,
+    meta: FileMeta,
+    /// Set for symlinks: the link's own target, never read or hashed.
+    link_target: Option<String>,
+}
+
+impl FsFile {
+    fn inode_key(&self) -> Option<(i64, i64)> {
+        Some((self.meta.dev?, self.meta.inode?))
+    }
+}
+
+struct Listing {
+    files: Vec<FsFile>,
+    subdirs: Vec<PathBuf>,
+    /// Entries that could not be stat'ed; logged by the DB writer.
+    errors: Vec<String>,
+    /// Every entry was listed and the subdirectories will be visited:
+    /// nothing was skipped by a filter or an error, and the scan is
+    /// recursive.
+    complete: bool,
+}
+
 pub fn run(args: &ScanArgs, config: &Config, db: &Db) -> Result<()> {
-    let opts = ScanOptions::from_args_and_config(args, config)?;
+    let opts = Arc::new(ScanOptions::from_args_and_config(args, config)?);
 
     let dirs: Vec<PathBuf> = if args.dirs.is_empty() {
         vec![std::env::current_dir()?]
@@ -75,216 +156,538 @@ pub fn run(args: &ScanArgs, config: &Config, db: &Db) -> Result<()> {
     let mut progress = ScanProgress::new();
     progress.start()?;
 
-    let mut queue: VecDeque<PathBuf> = VecDeque::new();
+    // Workers only touch the filesystem; every DB write happens on this thread.
+    let (tx, rx) = mpsc::channel::<Outcome>();
+    let enumerate = {
+        let opts = Arc::clone(&opts);
+        Pool::new(opts.jobs, tx.clone(), move |job: EnumerateJob| {
+            let listing = enumerate_dir(&job.dir, &opts, job.subdirs_only);
+            Outcome::Listed(job, listing)
+        })
+    };
+    let fast = Pool::new(opts.jobs, tx.clone(), |job: FastHashJob| {
+        let hash = hash::fast_hash(&job.file.path);
+        Outcome::FastHashed(job, hash)
+    });
+    let full = Pool::new(opts.jobs, tx, |job: FullHashJob| {
+        let hash = hash::full_hash(&job.path);
+        Outcome::FullHashed(job, hash)
+    });
+
+    let mut pipeline = Pipeline {
+        db,
+        batch: WriteBatch::new(db, opts.batch_size)?,
+        opts: &opts,
+        progress: &mut progress,
+        enumerate: &enumerate,
+        fast: &fast,
+        full: &full,
+        outstanding: 0,
+        visited: HashSet::new(),
+        scope: Vec::new(),
+        pending_per_dir: HashMap::new(),
+        incomplete: HashSet::new(),
+        links_in_flight: HashMap::new(),
+    };
+
     for dir in dirs {
         match dir.canonicalize() {
-            Ok(canonical) => queue.push_back(canonical),
+            Ok(canonical) => pipeline.visit(canonical)?,
             Err(e) => {
-                progress.log(format!("Skipping {}: {}", dir.display(), e));
+                pipeline
+                    .progress
+                    .log(format!("Skipping {}: {}", dir.display(), e));
             }
         }
     }
 
-    while let Some(dir_path) = queue.pop_front() {
+    // Phase 1: enumerate and fast-hash everything; phase 2: full-hash the
+    // collision candidates once, now that all fast hashes are known.
+    if pipeline.drain(&rx)? {
+        pipeline.queue_full_hashes()?;
+        pipeline.drain(&rx)?;
+    }
+
+    pipeline.commit()?;
+    drop((enumerate, fast, full));
+
+    // Final duplicate count
+    let groups: Vec<_> = db
+        .duplicate_groups()?
+        .into_iter()
+        .filter(|g| !g.is_hard_linked())
+        .collect();
+    let reclaimable = groups.iter().map(|g| g.reclaimable_bytes()).sum();
+    progress.finish(groups.len(), reclaimable)?;
+
+    Ok(())
+}
+
+/// State of one scan run, owned by the single DB-writer thread.
+struct Pipeline<'a> {
+    db: &'a Db,
+    batch: WriteBatch<'a>,
+    opts: &'a ScanOptions,
+    progress: &'a mut ScanProgress,
+    enumerate: &'a Pool<EnumerateJob>,
+    fast: &'a Pool<FastHashJob>,
+    full: &'a Pool<FullHashJob>,
+    /// Jobs submitted to any pool whose outcome has not been handled yet.
+    outstanding: usize,
+    /// Canonical directories already queued, so symlink cycles are walked once.
+    visited: HashSet<PathBuf>,
+    /// Ids of every directory visited, for `FullHashScope::Scanned`.
+    scope: Vec<i64>,
+    /// Fast hashes still in flight, per directory id.
+    pending_per_dir: HashMap<i64, usize>,
+    /// Directories in flight whose listing is incomplete or one of whose
+    /// files failed to hash.
+    incomplete: HashSet<i64>,
+    /// Inodes with a fast hash in flight, and other hard links to them that
+    /// are waiting for that result instead of being read again.
+    links_in_flight: HashMap<(i64, i64), Vec<FastHashJob>>,
+}
+
+impl Pipeline<'_> {
+    fn visit(&mut self, dir_path: PathBuf) -> Result<()> {
+        if !self.visited.insert(dir_path.clone()) {
+            return Ok(());
+        }
         let dir_str = dir_path.to_string_lossy().into_owned();
-        progress.set_current_dir(dir_str.clone());
 
         // Get or create directory record
-        let dir_id = db.upsert_directory(&dir_str)?;
-        let dir_row = db.get_directory(&dir_str)?.unwrap();
+        let dir_id = self.db.upsert_directory(&dir_str)?;
+        self.batch.wrote(1)?;
+        self.scope.push(dir_id);
+        let dir_row = self.db.get_directory(&dir_str)?.unwrap();
 
         // Skip if already scanned and rescan not requested
-        if dir_row.last_scanned.is_some() && !opts.rescan {
-            if opts.recursive {
-                enqueue_subdirs(&dir_path, &opts, &mut queue);
+        let already_scanned = dir_row.last_scanned.is_some() && !self.opts.rescan;
+        if already_scanned && !self.opts.recursive {
+            return Ok(());
+        }
+
+        self.enumerate.submit(EnumerateJob {
+            dir: dir_path,
+            dir_id,
+            subdirs_only: already_scanned,
+        });
+        self.outstanding += 1;
+        Ok(())
+    }
+
+    /// Handle outcomes until every submitted job is done. Returns false if
+    /// the user interrupted the scan.
+    fn drain(&mut self, rx: &Receiver<Outcome>) -> Result<bool> {
+        while self.outstanding > 0 {
+            if self.progress.interrupted() {
+                // Directories still in flight keep last_scanned = NULL and are
+                // picked up again by the next scan.
+                self.progress
+                    .log("Interrupted — committing finished work".into());
+                return Ok(false);
             }
-            continue;
+            match rx.recv_timeout(LOAD_REFRESH) {
+                Ok(outcome) => {
+                    self.outstanding -= 1;
+                    self.handle(outcome)?;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            self.report_load();
+        }
+        Ok(true)
+    }
+
+    fn handle(&mut self, outcome: Outcome) -> Result<()> {
+        match outcome {
+            Outcome::Listed(job, listing) => self.on_listed(job, listing),
+            Outcome::FastHashed(job, hash) => self.on_fast_hashed(job, hash),
+            Outcome::FullHashed(job, hash) => self.on_full_hashed(job, hash),
+        }
+    }
+
+    fn on_listed(&mut self, job: EnumerateJob, listing: Result<Listing>) -> Result<()> {
+        let listing = match listing {
+            Ok(l) => l,
+            Err(e) => {
+                self.progress
+                    .log(format!("Cannot read {}: {}", job.dir.display(), e));
+                // What an earlier scan recorded may be out of date now
+                self.db.set_directory_incomplete(job.dir_id)?;
+                self.batch.wrote(1)?;
+                return Ok(());
+            }
+        };
+
+        if job.subdirs_only {
+            for subdir in listing.subdirs {
+                self.visit(subdir)?;
+            }
+            return Ok(());
         }
 
-        // Enumerate filesystem entries
-        let (fs_files, fs_subdirs) = enumerate_dir(&dir_path, &opts)?;
+        let dir_str = job.dir.to_string_lossy().into_owned();
+        self.progress.set_current_dir(dir_str.clone());
+        for msg in listing.errors {
+            self.progress.log(msg);
+        }
+        if !listing.complete {
+            self.incomplete.insert(job.dir_id);
+        }
 
         // Load existing DB files for this directory
-        let db_files = db.files_in_directory(dir_id)?;
+        let db_files = self.db.files_in_directory(job.dir_id)?;
 
         // Deletion detection: files in DB but not in FS
-        let fs_file_names: std::collections::HashSet<&str> =
-            fs_files.iter().map(|(n, _)| n.as_str()).collect();
+        let fs_file_names: HashSet<&str> = listing.files.iter().map(|f| f.name.as_str()).collect();
         for db_file in &db_files {
             // If we're not scanning hidden files, skip hidden DB entries for deletion check
-            if !opts.hidden && ScanOptions::is_hidden(&db_file.name) {
+            if !self.opts.hidden && ScanOptions::is_hidden(&db_file.name) {
+                self.incomplete.insert(job.dir_id);
                 continue;
             }
             if !fs_file_names.contains(db_file.name.as_str()) {
-                db.delete_file(db_file.id)?;
-                progress.inc_deleted();
+                self.db.delete_file(db_file.id)?;
+                self.batch.wrote(1)?;
+                self.progress.inc_deleted();
             }
         }
 
         // Directory deletion detection: child dirs in DB but not on the filesystem
-        let fs_subdir_set: std::collections::HashSet<String> =
-            fs_subdirs.iter().map(|p| p.to_string_lossy().into_owned()).collect();
-        for child in db.child_directories(&dir_str)? {
+        let fs_subdir_set: HashSet<String> = listing
+            .subdirs
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+        for child in self.db.child_directories(&dir_str)? {
             if !fs_subdir_set.contains(&child.canonical_path) {
-                db.delete_directory_tree(&child.canonical_path)?;
-                progress.log(format!("Removed deleted directory: {}", child.canonical_path));
+                self.db.delete_directory_tree(&child.canonical_path)?;
+                self.batch.wrote(1)?;
+                self.progress
+                    .log(format!("Removed deleted directory: {}", child.canonical_path));
             }
         }
 
-        // Process each filesystem file
-        let db_file_map: std::collections::HashMap<&str, &crate::db::FileRow> =
+        // New or changed files go to the fast-hash stage
+        let db_file_map: HashMap<&str, &crate::db::FileRow> =
             db_files.iter().map(|f| (f.name.as_str(), f)).collect();
-
-        for (name, full_path) in &fs_files {
-            let meta = match std::fs::metadata(full_path) {
-                Ok(m) => m,
-                Err(e) => {
-                    progress.log(format!("Cannot stat {}: {}", full_path.display(), e));
+        let mut pending = 0;
+        for file in listing.files {
+            if let Some(existing) = db_file_map.get(file.name.as_str()) {
+                if existing.size == file.meta.size
+                    && existing.modified_at == file.meta.modified_at
+                    && existing.link_target == file.link_target
+                {
+                    // Unchanged — skip, but keep the link count and inode current
+                    if existing.dev != file.meta.dev
+                        || existing.inode != file.meta.inode
+                        || existing.nlink != file.meta.nlink
+                    {
+                        self.db.update_inode(existing.id, &file.meta)?;
+                        self.batch.wrote(1)?;
+                    }
+                    self.progress.inc_scanned();
                     continue;
                 }
-            };
-            let size = meta.len() as i64;
-            let modified_at = system_time_to_secs(meta.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+            }
 
-            let full_path_str = full_path.to_string_lossy().into_owned();
+            // Symlinks are recorded as links, never hashed
+            if let Some(target) = &file.link_target {
+                self.db.upsert_symlink(
+                    job.dir_id,
+                    &file.name,
+                    &file.path.to_string_lossy(),
+                    &file.meta,
+                    target,
+                )?;
+                self.batch.wrote(1)?;
+                self.progress.inc_scanned();
+                continue;
+            }
 
-            if let Some(existing) = db_file_map.get(name.as_str()) {
-                if existing.size == size && existing.modified_at == modified_at {
-                    // Unchanged — skip
-                    progress.inc_scanned();
+            // Hard link to an inode that is already hashed: reuse its hashes
+            if file.meta.nlink > 1 {
+                if let Some(link) = self.db.hashed_link(&file.meta)? {
+                    self.db.upsert_file(
+                        job.dir_id,
+                        &file.name,
+                        &file.path.to_string_lossy(),
+                        &file.meta,
+                        link.fast_hash.as_deref(),
+                        link.full_hash.as_deref(),
+                    )?;
+                    self.batch.wrote(1)?;
+                    self.progress.inc_scanned();
                     continue;
                 }
-                // Changed — recompute fast hash, clear full hash
-                match hash::fast_hash(full_path) {
-                    Ok(fh) => {
-                        db.upsert_file(dir_id, name, &full_path_str, size, modified_at, Some(&fh), None)?;
-                    }
-                    Err(e) => {
-                        progress.log(format!("fast_hash {}: {}", full_path.display(), e));
-                    }
-                }
-            } else {
-                // New file
-                match hash::fast_hash(full_path) {
-                    Ok(fh) => {
-                        db.upsert_file(dir_id, name, &full_path_str, size, modified_at, Some(&fh), None)?;
-                    }
-                    Err(e) => {
-                        progress.log(format!("fast_hash {}: {}", full_path.display(), e));
-                    }
+            }
+
+            let fast_job = FastHashJob {
+                dir_id: job.dir_id,
+                file,
+            };
+            pending += 1;
+            if let Some(key) = fast_job.file.inode_key().filter(|_| fast_job.file.meta.nlink > 1) {
+                if let Some(waiters) = self.links_in_flight.get_mut(&key) {
+                    waiters.push(fast_job);
+                    continue;
                 }
+                self.links_in_flight.insert(key, Vec::new());
             }
-            progress.inc_scanned();
+            self.fast.submit(fast_job);
+            self.outstanding += 1;
         }
 
-        // Compute full hashes for collision candidates
-        let candidates = db.candidates_needing_full_hash()?;
-        for file in candidates {
-            // Only process files under the current scan scope
-            let path = PathBuf::from(&file.canonical_path);
-            match hash::full_hash(&path) {
-                Ok(fh) => {
-                    db.update_full_hash(file.id, &fh)?;
-                    progress.inc_hashed();
-                }
-                Err(e) => {
-                    progress.log(format!("full_hash {}: {}", path.display(), e));
+        if pending == 0 {
+            self.finish_dir(job.dir_id)?;
+        } else {
+            self.pending_per_dir.insert(job.dir_id, pending);
+        }
+
+        if self.opts.recursive {
+            for subdir in listing.subdirs {
+                self.visit(subdir)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_fast_hashed(&mut self, job: FastHashJob, hash: Result<String>) -> Result<()> {
+        let waiters = job
+            .file
+            .inode_key()
+            .and_then(|key| self.links_in_flight.remove(&key))
+            .unwrap_or_default();
+        let hash = hash.map_err(|e| e.to_string());
+        for job in std::iter::once(job).chain(waiters) {
+            self.store_fast_hash(&job, &hash)?;
+        }
+        Ok(())
+    }
+
+    fn store_fast_hash(
+        &mut self,
+        job: &FastHashJob,
+        hash: &std::result::Result<String, String>,
+    ) -> Result<()> {
+        let file = &job.file;
+        match hash {
+            Ok(fh) => {
+                let path_str = file.path.to_string_lossy();
+                self.db
+                    .upsert_file(job.dir_id, &file.name, &path_str, &file.meta, Some(fh), None)?;
+                self.batch.wrote(1)?;
+            }
+            Err(e) => {
+                self.progress
+                    .log(format!("fast_hash {}: {}", file.path.display(), e));
+                self.incomplete.insert(job.dir_id);
+            }
+        }
+        self.progress.inc_scanned();
+
+        let remaining = self.pending_per_dir.get_mut(&job.dir_id).map(|n| {
+            *n -= 1;
+            *n
+        });
+        if remaining == Some(0) {
+            self.pending_per_dir.remove(&job.dir_id);
+            self.finish_dir(job.dir_id)?;
+        }
+        Ok(())
+    }
+
+    fn on_full_hashed(&mut self, job: FullHashJob, hash: Result<String>) -> Result<()> {
+        match hash {
+            Ok(fh) => {
+                for &id in &job.ids {
+                    self.db.update_full_hash(id, &fh)?;
+                    self.batch.wrote(1)?;
                 }
+                self.progress.inc_hashed();
+            }
+            Err(e) => {
+                self.progress
+                    .log(format!("full_hash {}: {}", job.path.display(), e));
             }
         }
+        Ok(())
+    }
 
-        // Mark directory as scanned
+    /// All fast hashes of a directory are stored: mark it scanned.
+    fn finish_dir(&mut self, dir_id: i64) -> Result<()> {
         let now = system_time_to_secs(SystemTime::now());
-        db.set_directory_scanned(dir_id, now)?;
+        let complete = !self.incomplete.remove(&dir_id);
+        self.db.set_directory_scanned(dir_id, now, complete)?;
+        self.batch.wrote(1)?;
+        Ok(())
+    }
+
+    /// Send the size+fast_hash collision candidates to the full-hash stage.
+    fn queue_full_hashes(&mut self) -> Result<()> {
+        let candidates = match self.opts.full_hash_scope {
+            FullHashScope::All => self.db.candidates_needing_full_hash()?,
+            FullHashScope::Scanned => self.db.candidates_needing_full_hash_in(&self.scope)?,
+        };
 
-        if opts.recursive {
-            for subdir in fs_subdirs {
-                queue.push_back(subdir);
+        // Hard links to one inode are read once
+        let mut jobs: Vec<FullHashJob> = Vec::new();
+        let mut by_inode: HashMap<(i64, i64), usize> = HashMap::new();
+        for file in candidates {
+            if let Some(&i) = file.inode_key().and_then(|k| by_inode.get(&k)) {
+                jobs[i].ids.push(file.id);
+                continue;
+            }
+            if let Some(key) = file.inode_key() {
+                by_inode.insert(key, jobs.len());
             }
+            jobs.push(FullHashJob {
+                ids: vec![file.id],
+                path: PathBuf::from(&file.canonical_path),
+            });
+        }
+
+        self.progress.begin_full_hash(jobs.len());
+        for job in jobs {
+            self.full.submit(job);
+            self.outstanding += 1;
         }
+        Ok(())
     }
 
-    // Final duplicate count
-    let groups = db.duplicate_groups()?;
-    progress.finish(groups.len())?;
+    fn commit(self) -> Result<()> {
+        self.batch.commit()
+    }
 
-    Ok(())
+    fn report_load(&mut self) {
+        self.progress.set_load(Stage::Enumerate, self.enumerate.load());
+        self.progress.set_load(Stage::FastHash, self.fast.load());
+        self.progress.set_load(Stage::FullHash, self.full.load());
+    }
 }
 
 // ── Helpers ──────────────────────────────────────────────────────────────────
 
-fn enumerate_dir(
-    dir: &Path,
-    opts: &ScanOptions,
-) -> Result<(Vec<(String, PathBuf)>, Vec<PathBuf>)> {
-    let mut files = Vec::new();
-    let mut subdirs = Vec::new();
-
-    let read_dir = match std::fs::read_dir(dir) {
-        Ok(rd) => rd,
-        Err(e) => return Err(e.into()),
+fn enumerate_dir(dir: &Path, opts: &ScanOptions, subdirs_only: bool) -> Result<Listing> {
+    let mut listing = Listing {
+        files: Vec::new(),
+        subdirs: Vec::new(),
+        errors: Vec::new(),
+        complete: opts.recursive,
     };
 
-    for entry in read_dir.flatten() {
+    for entry in std::fs::read_dir(dir)?.flatten() {
         let name = entry.file_name().to_string_lossy().into_owned();
 
         if !opts.hidden && ScanOptions::is_hidden(&name) {
+            listing.complete = false;
             continue;
         }
 
+        // A dangling symlink has no target type even when following links;
+        // it is recorded as a link.
         let file_type = if opts.follow_symlinks {
-            entry.metadata().map(|m| m.file_type())
+            entry
+                .metadata()
+                .map(|m| m.file_type())
+                .or_else(|_| entry.file_type())
         } else {
-            entry.file_type().map(|ft| ft)
+            entry.file_type()
         };
 
         let ft = match file_type {
             Ok(ft) => ft,
-            Err(_) => continue,
+            Err(_) => {
+                listing.complete = false;
+                continue;
+            }
         };
 
         if ft.is_dir() {
             // Resolve to canonical to avoid following the same dir twice via symlinks
-            if let Ok(canonical) = entry.path().canonicalize() {
-                subdirs.push(canonical);
+            match entry.path().canonicalize() {
+                Ok(canonical) => {
+                    // A followed link to a directory elsewhere is not part of
+                    // this directory's tree in the DB.
+                    if canonical.parent() != Some(dir) {
+                        listing.complete = false;
+                    }
+                    listing.subdirs.push(canonical);
+                }
+                Err(_) => listing.complete = false,
+            }
+        } else if ft.is_file() && !subdirs_only {
+            if !opts.file_included(&name) {
+                listing.complete = false;
+                continue;
             }
-        } else if ft.is_file() {
+            let canonical = entry.path().canonicalize().unwrap_or_else(|_| entry.path());
+            let meta = match std::fs::metadata(&canonical) {
+                Ok(m) => m,
+                Err(e) => {
+                    listing
+                        .errors
+                        .push(format!("Cannot stat {}: {}", canonical.display(), e));
+                    listing.complete = false;
+                    continue;
+                }
+            };
+            listing.files.push(FsFile {
+                name,
+                path: canonical,
+                meta: file_meta(&meta),
+                link_target: None,
+            });
+        } else if ft.is_symlink() && !subdirs_only {
+            // Symlinks not followed are stored as links (is_file()/is_dir()
+            // return false for them), with the link's own metadata.
             if !opts.file_included(&name) {
+                listing.complete = false;
                 continue;
             }
-            let canonical = if opts.follow_symlinks {
-                entry.path().canonicalize().unwrap_or_else(|_| entry.path())
-            } else {
-                entry.path().canonicalize().unwrap_or_else(|_| entry.path())
+            let path = dir.join(&name);
+            let (meta, target) = match std::fs::symlink_metadata(&path)
+                .and_then(|m| Ok((m, std::fs::read_link(&path)?)))
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    listing
+                        .errors
+                        .push(format!("Cannot read link {}: {}", path.display(), e));
+                    listing.complete = false;
+                    continue;
+                }
             };
-            files.push((name, canonical));
+            listing.files.push(FsFile {
+                name,
+                path,
+                meta: file_meta(&meta),
+                link_target: Some(target.to_string_lossy().into_owned()),
+            });
         }
-        // Symlinks not followed are skipped (is_file()/is_dir() returns false for symlinks when not following)
     }
 
-    Ok((files, subdirs))
+    Ok(listing)
 }
 
-fn enqueue_subdirs(dir: &Path, opts: &ScanOptions, queue: &mut VecDeque<PathBuf>) {
-    if let Ok(read_dir) = std::fs::read_dir(dir) {
-        for entry in read_dir.flatten() {
-            let name = entry.file_name().to_string_lossy().into_owned();
-            if !opts.hidden && ScanOptions::is_hidden(&name) {
-                continue;
-            }
-            let ft = if opts.follow_symlinks {
-                entry.metadata().map(|m| m.file_type())
-            } else {
-                entry.file_type().map(|ft| ft)
-            };
-            if let Ok(ft) = ft {
-                if ft.is_dir() {
-                    if let Ok(canonical) = entry.path().canonicalize() {
-                        queue.push_back(canonical);
-                    }
-                }
-            }
-        }
+/// Stat data stored in the `files` table for a file's metadata.
+pub fn file_meta(meta: &std::fs::Metadata) -> FileMeta {
+    #[cfg(unix)]
+    let (dev, inode, nlink) = {
+        use std::os::unix::fs::MetadataExt;
+        (Some(meta.dev() as i64), Some(meta.ino() as i64), meta.nlink() as i64)
+    };
+    #[cfg(not(unix))]
+    let (dev, inode, nlink) = (None, None, 1);
+
+    FileMeta {
+        size: meta.len() as i64,
+        modified_at: system_time_to_secs(meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)),
+        dev,
+        inode,
+        nlink,
     }
 }
 
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
@@ -8,18 +9,40 @@ use ratatui::{
     Terminal,
 };
 use std::io::Stdout;
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::pool::Load;
 use crate::tui;
 
+/// Minimum time between two redraws of the progress screen.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Pipeline stages whose worker load is shown in the status panel.
+#[derive(Clone, Copy)]
+pub enum Stage {
+    Enumerate,
+    FastHash,
+    FullHash,
+}
+
 /// Live scan progress state, rendered to the terminal.
 pub struct ScanProgress {
     current_dir: String,
     files_scanned: u64,
     files_hashed: u64,
     files_deleted: u64,
+    /// Worker load per stage, indexed by `Stage`.
+    loads: [Load; 3],
     log_lines: Vec<String>,
     start: Instant,
+    /// Start of the full-hash phase; None while still enumerating/fast hashing.
+    full_hash_start: Option<Instant>,
+    full_hash_total: usize,
+    last_draw: Option<Instant>,
+    /// Set by Ctrl-C: a SIGINT in plain mode, a key event in raw mode.
+    interrupt: Arc<AtomicBool>,
     /// None when not in a TTY — falls back to plain stdout output.
     terminal: Option<Terminal<CrosstermBackend<Stdout>>>,
 }
@@ -31,14 +54,21 @@ impl ScanProgress {
             files_scanned: 0,
             files_hashed: 0,
             files_deleted: 0,
+            loads: [Load::default(); 3],
             log_lines: Vec::new(),
             start: Instant::now(),
+            full_hash_start: None,
+            full_hash_total: 0,
+            last_draw: None,
+            interrupt: Arc::new(AtomicBool::new(false)),
             terminal: None,
         }
     }
 
     pub fn start(&mut self) -> Result<()> {
         self.start = Instant::now();
+        let interrupt = Arc::clone(&self.interrupt);
+        let _ = ctrlc::set_handler(move || interrupt.store(true, Ordering::SeqCst));
         match tui::enter() {
             Ok(t) => {
                 self.terminal = Some(t);
@@ -76,6 +106,35 @@ impl ScanProgress {
         let _ = self.render();
     }
 
+    /// Enumeration and fast hashing are done; `candidates` files will now
+    /// get a full hash.
+    pub fn begin_full_hash(&mut self, candidates: usize) {
+        self.full_hash_start = Some(Instant::now());
+        self.full_hash_total = candidates;
+        if self.terminal.is_some() {
+            let _ = self.render();
+        } else {
+            eprintln!("Full-hash phase: {} candidates", candidates);
+        }
+    }
+
+    pub fn set_load(&mut self, stage: Stage, load: Load) {
+        self.loads[stage as usize] = load;
+        let _ = self.render();
+    }
+
+    /// True once the user has pressed Ctrl-C.
+    pub fn interrupted(&mut self) -> bool {
+        if self.terminal.is_some() {
+            if let Ok(Some(key)) = tui::next_key(Duration::ZERO) {
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    self.interrupt.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+        self.interrupt.load(Ordering::SeqCst)
+    }
+
     pub fn log(&mut self, msg: String) {
         if self.terminal.is_none() {
             eprintln!("{}", msg);
@@ -87,19 +146,42 @@ impl ScanProgress {
         let _ = self.render();
     }
 
-    pub fn finish(mut self, duplicate_groups: usize) -> Result<()> {
+    pub fn finish(mut self, duplicate_groups: usize, reclaimable: i64) -> Result<()> {
         if let Some(ref mut t) = self.terminal {
             tui::leave(t)?;
         }
         let elapsed = self.start.elapsed();
+        let outcome = if self.interrupt.load(Ordering::SeqCst) {
+            "interrupted after"
+        } else {
+            "complete in"
+        };
         println!(
-            "Scan complete in {:.1}s — {} files scanned, {} hashed, {} deleted, {} duplicate groups",
+            "Scan {} {:.1}s — {} files scanned, {} hashed, {} deleted, {} duplicate groups ({} reclaimable)",
+            outcome,
             elapsed.as_secs_f64(),
             self.files_scanned,
             self.files_hashed,
             self.files_deleted,
             duplicate_groups,
+            tui::fmt_size(reclaimable),
+        );
+        let fast_elapsed = self
+            .full_hash_start
+            .map_or(elapsed, |t| t.duration_since(self.start));
+        println!(
+            "  Fast phase: {:.1}s — {} files scanned",
+            fast_elapsed.as_secs_f64(),
+            self.files_scanned,
         );
+        if let Some(t) = self.full_hash_start {
+            println!(
+                "  Full phase: {:.1}s — {} of {} candidates hashed",
+                t.elapsed().as_secs_f64(),
+                self.files_hashed,
+                self.full_hash_total,
+            );
+        }
         Ok(())
     }
 
@@ -107,19 +189,33 @@ impl ScanProgress {
         let Some(ref mut terminal) = self.terminal else {
             return Ok(());
         };
+        if self.last_draw.is_some_and(|t| t.elapsed() < REDRAW_INTERVAL) {
+            return Ok(());
+        }
+        self.last_draw = Some(Instant::now());
 
         let current_dir = self.current_dir.clone();
         let files_scanned = self.files_scanned;
         let files_hashed = self.files_hashed;
         let files_deleted = self.files_deleted;
         let elapsed = self.start.elapsed();
+        let phase = match self.full_hash_start {
+            None => format!("1/2 enumerate + fast hash ({:.1}s)", elapsed.as_secs_f64()),
+            Some(t) => format!("2/2 full hash ({:.1}s)", t.elapsed().as_secs_f64()),
+        };
+        let hashed = if self.full_hash_start.is_some() {
+            format!("{} of {}", files_hashed, self.full_hash_total)
+        } else {
+            files_hashed.to_string()
+        };
+        let loads = self.loads;
         let log_lines = self.log_lines.clone();
 
         terminal.draw(|f| {
             let area = f.area();
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([Constraint::Length(7), Constraint::Min(0)])
+                .constraints([Constraint::Length(11), Constraint::Min(0)])
                 .split(area);
 
             // Status panel
@@ -132,14 +228,17 @@ impl ScanProgress {
                     Span::styled("Scanning: ", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(truncated_dir),
                 ]),
-                Line::from(""),
+                Line::from(vec![
+                    Span::styled("Phase:          ", Style::default().fg(Color::Blue)),
+                    Span::raw(phase),
+                ]),
                 Line::from(vec![
                     Span::styled("Files scanned:  ", Style::default().fg(Color::Cyan)),
                     Span::raw(files_scanned.to_string()),
                 ]),
                 Line::from(vec![
                     Span::styled("Files hashed:   ", Style::default().fg(Color::Yellow)),
-                    Span::raw(files_hashed.to_string()),
+                    Span::raw(hashed),
                 ]),
                 Line::from(vec![
                     Span::styled("Files deleted:  ", Style::default().fg(Color::Red)),
@@ -149,6 +248,10 @@ impl ScanProgress {
                     Span::styled("Elapsed:        ", Style::default().fg(Color::Green)),
                     Span::raw(elapsed_str),
                 ]),
+                Line::from(""),
+                load_line("Enumerate:      ", loads[Stage::Enumerate as usize]),
+                load_line("Fast hash:      ", loads[Stage::FastHash as usize]),
+                load_line("Full hash:      ", loads[Stage::FullHash as usize]),
             ];
 
             let status = Paragraph::new(status_text)
@@ -167,3 +270,13 @@ impl ScanProgress {
         Ok(())
     }
 }
+
+fn load_line(label: &'static str, load: Load) -> Line<'static> {
+    Line::from(vec![
+        Span::styled(label, Style::default().fg(Color::Magenta)),
+        Span::raw(format!(
+            "{}/{} busy, {} queued",
+            load.busy, load.workers, load.queued
+        )),
+    ])
+}
@@ -0,0 +1,199 @@
+//! Deletion backend for the freedesktop.org Trash specification: files are
+//! moved into `$XDG_DATA_HOME/Trash` or the per-volume `.Trash/$UID` /
+//! `.Trash-$UID` directory, next to a `.trashinfo` file that lets file
+//! managers restore them.
+#![cfg_attr(not(unix), allow(dead_code))]
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Move `path` into the trash of its volume. Returns its new location.
+#[cfg(unix)]
+pub fn trash(path: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::symlink_metadata(path)
+        .with_context(|| format!("reading {}", path.display()))?
+        .dev();
+
+    let home = home_trash()?;
+    let (dir, info_path) = if device_of(&home)? == dev {
+        (home, path.to_path_buf())
+    } else {
+        let top = mount_point(path, dev)?;
+        let dir = volume_trash(&top).with_context(|| {
+            format!(
+                "no usable trash on the volume of {} (use --action delete to delete it permanently)",
+                path.display()
+            )
+        })?;
+        // Paths in a volume trash are relative to its top directory.
+        let rel = path.strip_prefix(&top).unwrap_or(path).to_path_buf();
+        (dir, rel)
+    };
+    move_into(&dir, path, &info_path)
+}
+
+#[cfg(not(unix))]
+pub fn trash(path: &Path) -> Result<PathBuf> {
+    bail!(
+        "the freedesktop.org trash is not available on this platform; \
+         use --action delete to delete {} permanently",
+        path.display()
+    )
+}
+
+/// `$XDG_DATA_HOME/Trash`, defaulting to `~/.local/share/Trash`.
+fn home_trash() -> Result<PathBuf> {
+    let data_home = match std::env::var_os("XDG_DATA_HOME") {
+        Some(d) if !d.is_empty() => PathBuf::from(d),
+        _ => {
+            let home = std::env::var_os("HOME").context("HOME is not set")?;
+            PathBuf::from(home).join(".local/share")
+        }
+    };
+    Ok(data_home.join("Trash"))
+}
+
+/// Device of `path`, or of its nearest existing ancestor when the trash
+/// directory has not been created yet.
+#[cfg(unix)]
+fn device_of(path: &Path) -> Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    for dir in path.ancestors() {
+        if let Ok(meta) = std::fs::metadata(dir) {
+            return Ok(meta.dev());
+        }
+    }
+    bail!("cannot stat any parent of {}", path.display())
+}
+
+/// Top directory of the volume holding `path`: the last ancestor on the
+/// same device.
+#[cfg(unix)]
+fn mount_point(path: &Path, dev: u64) -> Result<PathBuf> {
+    use std::os::unix::fs::MetadataExt;
+    let mut top = path.parent().context("path has no parent")?;
+    while let Some(parent) = top.parent() {
+        if std::fs::metadata(parent)?.dev() != dev {
+            break;
+        }
+        top = parent;
+    }
+    Ok(top.to_path_buf())
+}
+
+/// `$top/.Trash/$uid` when the administrator set up a shared `.Trash`
+/// (a real directory with the sticky bit), otherwise `$top/.Trash-$uid`.
+#[cfg(unix)]
+fn volume_trash(top: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    let shared = top.join(".Trash");
+    if let Ok(meta) = std::fs::symlink_metadata(&shared) {
+        if meta.is_dir() && meta.permissions().mode() & 0o1000 != 0 {
+            let dir = shared.join(uid.to_string());
+            if std::fs::DirBuilder::new().mode(0o700).create(&dir).is_ok() || dir.is_dir() {
+                return Ok(dir);
+            }
+        }
+    }
+
+    let dir = top.join(format!(".Trash-{}", uid));
+    if !dir.is_dir() {
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("creating {}", dir.display()))?;
+    }
+    Ok(dir)
+}
+
+/// Reserve a name in `trash/info` with an exclusively created `.trashinfo`,
+/// then rename the file into `trash/files` under the same name.
+fn move_into(trash: &Path, path: &Path, info_path: &Path) -> Result<PathBuf> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    for dir in [&files, &info] {
+        std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+
+    let name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?
+        .to_string_lossy()
+        .into_owned();
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        url_encode(&info_path.to_string_lossy()),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    for n in 1.. {
+        let candidate = if n == 1 {
+            name.clone()
+        } else {
+            numbered(&name, n)
+        };
+        let info_file = info.join(format!("{}.trashinfo", candidate));
+        let dest = files.join(&candidate);
+        let created = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_file);
+        let mut f = match created {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("creating {}", info_file.display())),
+        };
+        // A stray file without info entry must not be overwritten either.
+        if dest.symlink_metadata().is_ok() {
+            let _ = std::fs::remove_file(&info_file);
+            continue;
+        }
+        let moved = std::io::Write::write_all(&mut f, contents.as_bytes())
+            .map_err(anyhow::Error::from)
+            .and_then(|()| std::fs::rename(path, &dest).map_err(anyhow::Error::from));
+        if let Err(e) = moved {
+            let _ = std::fs::remove_file(&info_file);
+            return Err(e).with_context(|| format!("moving {} to the trash", path.display()));
+        }
+        return Ok(dest);
+    }
+    unreachable!()
+}
+
+/// `name.ext` → `name.N.ext`, used when the trash already has `name.ext`.
+pub fn numbered(name: &str, n: u32) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}.{}.{}", stem, n, ext),
+        _ => format!("{}.{}", name, n),
+    }
+}
+
+/// Percent-encode a path for the `Path=` key, keeping `/` and the RFC 2396
+/// unreserved characters.
+fn url_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'/'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
@@ -0,0 +1,544 @@
+use anyhow::Result;
+use crossterm::event::KeyCode;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::action::{self, ActionOptions};
+use crate::cli::RemoveAction;
+use crate::db::{bytes_freed, Db, DirectoryRow, FileRow};
+use crate::tui::{self, fmt_size};
+use crate::verify::KeptFileChanged;
+
+/// Directory trees with the same content, as of the last scan.
+pub struct TreeGroup {
+    /// Canonical paths of the copies, sorted.
+    pub dirs: Vec<String>,
+    /// Files in each copy, symbolic links included.
+    pub files: usize,
+    /// Bytes in each copy.
+    pub size: i64,
+}
+
+impl TreeGroup {
+    /// `dir X == dir Y (N files, size)`.
+    pub fn describe(&self) -> String {
+        let dirs: Vec<String> = self.dirs.iter().map(|d| format!("dir {}", d)).collect();
+        format!(
+            "{} ({} files, {})",
+            dirs.join(" == "),
+            self.files,
+            fmt_size(self.size)
+        )
+    }
+
+    /// Bytes freed by keeping a single copy.
+    pub fn reclaimable_bytes(&self) -> i64 {
+        self.size * (self.dirs.len() as i64 - 1)
+    }
+}
+
+/// Digest of a directory tree and what it holds.
+struct Tree {
+    /// None when the content is not fully known: the last scan of the
+    /// directory or of one below it skipped entries or did not finish, or a
+    /// file has no full hash.
+    digest: Option<blake3::Hash>,
+    files: usize,
+    size: i64,
+}
+
+/// Every set of directory trees with identical content: the same names,
+/// each file with the same full hash (or empty), each link with the same
+/// target. Only the largest trees are reported: when X and Y are copies,
+/// X/sub and Y/sub are not listed again. Trees that are hard links of each
+/// other throughout are left out, as removing them frees nothing.
+pub fn duplicate_trees(db: &Db) -> Result<Vec<TreeGroup>> {
+    let dirs = db.all_directories()?;
+    let mut files: HashMap<i64, Vec<FileRow>> = HashMap::new();
+    for file in db.all_files()? {
+        files.entry(file.directory_id).or_default().push(file);
+    }
+    let mut children: HashMap<i64, Vec<&DirectoryRow>> = HashMap::new();
+    for dir in &dirs {
+        if let Some(parent) = dir.parent_id {
+            children.entry(parent).or_default().push(dir);
+        }
+    }
+
+    // Deeper directories first, so every child is done before its parent.
+    let mut order: Vec<&DirectoryRow> = dirs.iter().collect();
+    order.sort_by_key(|d| Reverse(Path::new(&d.canonical_path).components().count()));
+    let mut trees: HashMap<i64, Tree> = HashMap::new();
+    for dir in order {
+        let subdirs: Vec<(&DirectoryRow, &Tree)> = children
+            .get(&dir.id)
+            .into_iter()
+            .flatten()
+            .map(|&c| (c, &trees[&c.id]))
+            .collect();
+        let files = files.get(&dir.id).map_or(&[][..], Vec::as_slice);
+        let tree = tree(dir, files, &subdirs);
+        trees.insert(dir.id, tree);
+    }
+
+    let mut by_digest: HashMap<blake3::Hash, Vec<&DirectoryRow>> = HashMap::new();
+    for dir in &dirs {
+        let tree = &trees[&dir.id];
+        if let (Some(digest), true) = (tree.digest, tree.files > 0) {
+            by_digest.entry(digest).or_default().push(dir);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for mut copies in by_digest.into_values() {
+        if copies.len() < 2 || within_copies(&copies, &trees) {
+            continue;
+        }
+        copies.sort_by(|a, b| a.canonical_path.cmp(&b.canonical_path));
+        let dirs: Vec<String> = copies.iter().map(|d| d.canonical_path.clone()).collect();
+        if hard_linked(&dirs, db)? {
+            continue;
+        }
+        let tree = &trees[&copies[0].id];
+        groups.push(TreeGroup {
+            dirs,
+            files: tree.files,
+            size: tree.size,
+        });
+    }
+    groups.sort_by(|a, b| {
+        b.reclaimable_bytes()
+            .cmp(&a.reclaimable_bytes())
+            .then_with(|| a.dirs.cmp(&b.dirs))
+    });
+    Ok(groups)
+}
+
+fn tree(dir: &DirectoryRow, files: &[FileRow], subdirs: &[(&DirectoryRow, &Tree)]) -> Tree {
+    let mut complete = dir.complete;
+    let mut entries: Vec<(String, String)> = Vec::new();
+    let mut out = Tree {
+        digest: None,
+        files: files.len(),
+        size: files.iter().map(|f| f.size).sum(),
+    };
+    for file in files {
+        let content = match (&file.link_target, &file.full_hash) {
+            (Some(target), _) => format!("link {}", target),
+            (None, Some(hash)) => format!("file {}", hash),
+            (None, None) if file.size == 0 => "file".to_owned(),
+            (None, None) => {
+                complete = false;
+                continue;
+            }
+        };
+        entries.push((file.name.clone(), content));
+    }
+    for (subdir, sub) in subdirs {
+        out.files += sub.files;
+        out.size += sub.size;
+        let name = Path::new(&subdir.canonical_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match sub.digest {
+            Some(digest) => entries.push((name, format!("dir {}", digest.to_hex()))),
+            None => complete = false,
+        }
+    }
+    if complete {
+        entries.sort();
+        let mut hasher = blake3::Hasher::new();
+        for (name, content) in &entries {
+            hasher.update(name.as_bytes());
+            hasher.update(b"\0");
+            hasher.update(content.as_bytes());
+            hasher.update(b"\0");
+        }
+        out.digest = Some(hasher.finalize());
+    }
+    out
+}
+
+/// The copies are subtrees at the same place in copies of their parents,
+/// which are reported instead.
+fn within_copies(copies: &[&DirectoryRow], trees: &HashMap<i64, Tree>) -> bool {
+    let parents: Vec<Option<i64>> = copies.iter().map(|d| d.parent_id).collect();
+    let digests: Vec<Option<blake3::Hash>> = parents
+        .iter()
+        .map(|p| p.and_then(|p| trees.get(&p)).and_then(|t| t.digest))
+        .collect();
+    let mut distinct = parents.clone();
+    distinct.sort();
+    distinct.dedup();
+    digests[0].is_some()
+        && digests.iter().all(|d| *d == digests[0])
+        && distinct.len() == parents.len()
+}
+
+/// Every file in every copy is a hard link to the same inode as the file
+/// at its place in the first copy.
+fn hard_linked(dirs: &[String], db: &Db) -> Result<bool> {
+    let first = db.files_under(&dirs[0])?;
+    for dir in &dirs[1..] {
+        let files = db.files_under(dir)?;
+        if files.len() != first.len()
+            || !first
+                .iter()
+                .zip(&files)
+                .all(|(a, b)| a.shares_inode_with(b))
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// The files of every copy of a tree group, as one list for the freed-bytes
+/// accounting, and which copies hold a protected path.
+struct Copies {
+    files: Vec<FileRow>,
+    /// Indexes into `files` of each copy's files.
+    ranges: Vec<Range<usize>>,
+    protected: Vec<bool>,
+}
+
+impl Copies {
+    fn load(group: &TreeGroup, opts: &ActionOptions, db: &Db) -> Result<Self> {
+        let mut copies = Copies {
+            files: Vec::new(),
+            ranges: Vec::new(),
+            protected: Vec::new(),
+        };
+        for dir in &group.dirs {
+            let files = db.files_under(dir)?;
+            copies.protected.push(
+                files
+                    .iter()
+                    .any(|f| opts.protected.contains(&f.canonical_path)),
+            );
+            let start = copies.files.len();
+            copies.files.extend(files);
+            copies.ranges.push(start..copies.files.len());
+        }
+        Ok(copies)
+    }
+
+    /// The copies removed when `keep` is kept: all others but the protected.
+    fn targets(&self, keep: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.ranges.len()).filter(move |&i| i != keep && !self.protected[i])
+    }
+
+    /// Bytes freed by keeping copy `keep` and removing the targets.
+    fn freed(&self, keep: usize) -> i64 {
+        let delete: Vec<usize> = self
+            .targets(keep)
+            .flat_map(|i| self.ranges[i].clone())
+            .collect();
+        bytes_freed(&self.files, &delete)
+    }
+
+    /// Each file of copy `target` with the file at its place in copy `keep`,
+    /// as indexes into `files`.
+    fn pairs(&self, dirs: &[String], keep: usize, target: usize) -> Vec<(usize, usize)> {
+        let relative = |i: usize, copy: usize| &self.files[i].canonical_path[dirs[copy].len()..];
+        let kept: HashMap<&str, usize> = self.ranges[keep]
+            .clone()
+            .map(|i| (relative(i, keep), i))
+            .collect();
+        self.ranges[target]
+            .clone()
+            .filter_map(|i| Some((*kept.get(relative(i, target))?, i)))
+            .collect()
+    }
+}
+
+/// `remove --trees`: pick the copy to keep of each duplicate tree in a
+/// TUI, then remove the files of the other copies and the directories left
+/// empty.
+pub fn run(opts: &ActionOptions, db: &Db) -> Result<()> {
+    let groups = duplicate_trees(db)?;
+    if groups.is_empty() {
+        println!("No duplicate directory trees found.");
+        return Ok(());
+    }
+
+    let copies: Vec<Copies> = groups
+        .iter()
+        .map(|g| Copies::load(g, opts, db))
+        .collect::<Result<_>>()?;
+    let mut keep: Vec<Option<usize>> = vec![None; groups.len()];
+    let mut terminal = tui::enter()?;
+    let result = choose(&mut terminal, &groups, &copies, &mut keep, opts.dry_run);
+    tui::leave(&mut terminal)?;
+    let decided = keep.iter().flatten().count();
+    if !result? {
+        if decided > 0 {
+            println!("Discarded {} decisions; nothing was removed.", decided);
+        }
+        return Ok(());
+    }
+    apply(&groups, &copies, &keep, opts, db)
+}
+
+/// Returns true when the decisions in `keep` are to be carried out.
+fn choose(
+    terminal: &mut tui::Term,
+    groups: &[TreeGroup],
+    copies: &[Copies],
+    keep: &mut [Option<usize>],
+    dry_run: bool,
+) -> Result<bool> {
+    let mut list_state = ListState::default();
+    list_state.select(Some(0));
+    let mut confirming = false;
+    let mut status_msg = String::new();
+
+    loop {
+        let cur = list_state.selected().unwrap_or(0);
+        let group = &groups[cur];
+        let decided = keep.iter().flatten().count();
+        let freed: i64 = copies
+            .iter()
+            .zip(keep.iter())
+            .filter_map(|(c, k)| Some(c.freed((*k)?)))
+            .sum();
+
+        terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(3),
+                    Constraint::Min(0),
+                    Constraint::Length(group.dirs.len() as u16 + 2),
+                    Constraint::Length(1),
+                    Constraint::Length(1),
+                ])
+                .split(f.area());
+
+            let dry_tag = if dry_run { " [DRY RUN]" } else { "" };
+            let header = Paragraph::new(format!(
+                "{} duplicate directory trees, {} decided, freeing {}",
+                groups.len(),
+                decided,
+                fmt_size(freed)
+            ))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!(" fdedupe — remove --trees{} ", dry_tag)),
+            );
+            f.render_widget(header, chunks[0]);
+
+            let items: Vec<ListItem> = groups
+                .iter()
+                .zip(keep.iter())
+                .map(|(g, k)| {
+                    let mark = match k {
+                        Some(k) => Span::styled(
+                            format!("[keep {}] ", k + 1),
+                            Style::default().fg(Color::Green),
+                        ),
+                        None => Span::raw("         "),
+                    };
+                    ListItem::new(Line::from(vec![mark, Span::raw(g.describe())]))
+                })
+                .collect();
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(" Trees "))
+                .highlight_style(Style::default().bg(Color::DarkGray));
+            f.render_stateful_widget(list, chunks[1], &mut list_state);
+
+            let lines: Vec<Line> = group
+                .dirs
+                .iter()
+                .enumerate()
+                .map(|(i, dir)| {
+                    let (label, style) = match keep[cur] {
+                        Some(k) if k == i => ("keep", Style::default().fg(Color::Green)),
+                        _ if copies[cur].protected[i] => {
+                            ("protected", Style::default().fg(Color::Yellow))
+                        }
+                        Some(_) => ("remove", Style::default().fg(Color::Red)),
+                        None => ("", Style::default()),
+                    };
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{}  ", i + 1),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::styled(format!("{:<9}  {}", label, dir), style),
+                    ])
+                })
+                .collect();
+            let pane = Paragraph::new(lines)
+                .block(Block::default().borders(Borders::ALL).title(" Copies "));
+            f.render_widget(pane, chunks[2]);
+
+            f.render_widget(
+                Paragraph::new(status_msg.as_str()).style(Style::default().fg(Color::Yellow)),
+                chunks[3],
+            );
+
+            let footer = if confirming {
+                format!(
+                    "  Remove the other copies of {} trees, freeing {}?   y confirm   n cancel",
+                    decided,
+                    fmt_size(freed)
+                )
+            } else {
+                "  1-9 keep that copy   u undecide   c commit   ↑↓ navigate   q quit".into()
+            };
+            f.render_widget(
+                Paragraph::new(Line::from(footer)).style(Style::default().fg(Color::DarkGray)),
+                chunks[4],
+            );
+        })?;
+
+        let Some(key) = tui::next_key(Duration::from_millis(100))? else {
+            continue;
+        };
+        status_msg.clear();
+        if confirming {
+            match key.code {
+                KeyCode::Char('y') => return Ok(true),
+                KeyCode::Char('n') | KeyCode::Esc => confirming = false,
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Up => list_state.select(Some(cur.saturating_sub(1))),
+            KeyCode::Down => list_state.select(Some((cur + 1).min(groups.len() - 1))),
+            KeyCode::Char(c @ '1'..='9') => {
+                let i = c as usize - '1' as usize;
+                if i < group.dirs.len() {
+                    keep[cur] = Some(i);
+                    list_state.select(Some((cur + 1).min(groups.len() - 1)));
+                }
+            }
+            KeyCode::Char('u') => keep[cur] = None,
+            KeyCode::Char('c') if decided == 0 => {
+                status_msg = "No tree has a copy to keep yet.".into();
+            }
+            KeyCode::Char('c') => confirming = true,
+            KeyCode::Char('q') => return Ok(false),
+            _ => {}
+        }
+    }
+}
+
+/// Remove every copy but the kept one of each decided tree, file by file
+/// through `action::apply`. Copies holding a protected path are left whole.
+fn apply(
+    groups: &[TreeGroup],
+    copies: &[Copies],
+    keep: &[Option<usize>],
+    opts: &ActionOptions,
+    db: &Db,
+) -> Result<()> {
+    let dry = opts.dry_run;
+    // The other actions leave a link at every path, so the directories stay.
+    let prune = !dry
+        && matches!(
+            opts.action,
+            RemoveAction::Trash | RemoveAction::Delete | RemoveAction::Quarantine
+        );
+    let mut files = 0;
+    let mut trees = 0;
+    let mut freed = 0;
+    let mut failures = Vec::new();
+
+    for ((group, copies), keep) in groups.iter().zip(copies).zip(keep) {
+        let Some(k) = *keep else {
+            continue;
+        };
+        let kept = &group.dirs[k];
+        let rule = format!("keep tree {}", kept);
+        println!("Keep: {}", kept);
+        let mut removed = Vec::new();
+        for (i, target) in group.dirs.iter().enumerate() {
+            if i != k && copies.protected[i] {
+                println!("  left {} (holds protected paths)", target);
+            }
+        }
+        for i in copies.targets(k) {
+            let target = &group.dirs[i];
+            let mut done = 0;
+            let mut all_done = true;
+            for (keep_file, target_file) in copies.pairs(&group.dirs, k, i) {
+                let (keep_file, target_row) =
+                    (&copies.files[keep_file], &copies.files[target_file]);
+                match action::apply(opts, keep_file, target_row, Some(&rule), db) {
+                    Ok(()) => {
+                        done += 1;
+                        removed.push(target_file);
+                    }
+                    Err(e) => {
+                        all_done = false;
+                        failures.push(format!("Failed: {:#}", e));
+                        if e.is::<KeptFileChanged>() {
+                            break;
+                        }
+                    }
+                }
+            }
+            println!(
+                "  {} {} ({} files)",
+                if dry { "would remove" } else { "removed" },
+                target,
+                done
+            );
+            files += done;
+            if all_done {
+                trees += 1;
+                if prune {
+                    prune_empty(Path::new(target), db)?;
+                }
+            }
+        }
+        freed += bytes_freed(&copies.files, &removed);
+    }
+
+    for msg in &failures {
+        eprintln!("{}", msg);
+    }
+    println!();
+    println!(
+        "{}{} {} files in {} trees, freeing {}",
+        if dry { "[DRY RUN] " } else { "" },
+        if dry { "Would remove" } else { "Removed" },
+        files,
+        trees,
+        fmt_size(freed)
+    );
+    if !failures.is_empty() {
+        println!("{} refused or failed (see above)", failures.len());
+    }
+    Ok(())
+}
+
+/// Remove `dir` and the directories below it once they are empty, with
+/// their rows. Files the scan did not see keep their directories.
+fn prune_empty(dir: &Path, db: &Db) -> Result<()> {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                prune_empty(&entry.path(), db)?;
+            }
+        }
+    }
+    if std::fs::remove_dir(dir).is_ok() {
+        db.delete_directory_tree(&dir.to_string_lossy())?;
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{self, Event, KeyEvent},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -38,11 +38,6 @@ pub fn next_key(timeout: Duration) -> Result<Option<KeyEvent>> {
     Ok(None)
 }
 
-/// Check if a key event matches a bare key code (no modifiers).
-pub fn is_key(key: &KeyEvent, code: KeyCode) -> bool {
-    key.code == code && key.modifiers == KeyModifiers::NONE
-}
-
 /// Truncate a path string to `max_len` visible characters, keeping the tail.
 /// If the path is longer than `max_len`, the beginning is replaced with "…".
 pub fn truncate_path(path: &str, max_len: usize) -> String {
@@ -71,3 +66,25 @@ pub fn fmt_size(bytes: i64) -> String {
         format!("{} B", bytes)
     }
 }
+
+/// Parse a byte size such as `4096`, `512K`, `10MB` or `1.5G`; units are
+/// powers of 1024, as in `fmt_size`.
+pub fn parse_size(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let n: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", s))?;
+    let factor: i64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return Err(format!("unknown unit in '{}'; use B, K, M, G or T", s)),
+    };
+    Ok((n * factor as f64) as i64)
+}
@@ -0,0 +1,91 @@
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::db::FileRow;
+use crate::scan;
+
+/// The kept copy of a group no longer matches the database. Returned by
+/// `action::apply`; callers abandon the rest of the group on it.
+#[derive(Debug)]
+pub struct KeptFileChanged(pub String);
+
+impl std::fmt::Display for KeptFileChanged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "kept copy {}; group aborted", self.0)
+    }
+}
+
+impl std::error::Error for KeptFileChanged {}
+
+/// Check that `file` still has the size and mtime recorded by the last scan.
+/// A symbolic link row is checked as the link itself: it must still be a
+/// link with the recorded target.
+pub fn unchanged(file: &FileRow) -> Result<()> {
+    let stat = match &file.link_target {
+        Some(_) => std::fs::symlink_metadata(&file.canonical_path),
+        None => std::fs::metadata(&file.canonical_path),
+    };
+    let meta = match stat {
+        Ok(m) if file.link_target.is_some() && !m.is_symlink() => {
+            bail!("{} is no longer a symbolic link", file.canonical_path)
+        }
+        Ok(m) => scan::file_meta(&m),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            bail!("{} is missing", file.canonical_path)
+        }
+        Err(e) => return Err(e).with_context(|| format!("reading {}", file.canonical_path)),
+    };
+    if meta.size != file.size {
+        bail!(
+            "{} changed size since the scan ({} → {} bytes)",
+            file.canonical_path,
+            file.size,
+            meta.size
+        );
+    }
+    if meta.modified_at != file.modified_at {
+        bail!("{} was modified since the scan", file.canonical_path);
+    }
+    if let Some(target) = &file.link_target {
+        let now = std::fs::read_link(&file.canonical_path)
+            .with_context(|| format!("reading link {}", file.canonical_path))?;
+        if now != Path::new(target) {
+            bail!("{} now points to {}", file.canonical_path, now.display());
+        }
+    }
+    Ok(())
+}
+
+/// Compare two files byte by byte.
+pub fn same_content(a: &Path, b: &Path) -> Result<bool> {
+    let mut fa = File::open(a).with_context(|| format!("opening {}", a.display()))?;
+    let mut fb = File::open(b).with_context(|| format!("opening {}", b.display()))?;
+    if fa.metadata()?.len() != fb.metadata()?.len() {
+        return Ok(false);
+    }
+    let mut buf_a = vec![0u8; 64 * 1024];
+    let mut buf_b = vec![0u8; 64 * 1024];
+    loop {
+        let n = read_full(&mut fa, &mut buf_a)?;
+        if n != read_full(&mut fb, &mut buf_b)? || buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buf` as far as the file allows; short only at end of file.
+fn read_full(f: &mut File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match f.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}